@@ -0,0 +1,12 @@
+use super::mermaid_id;
+use std::error::Error;
+
+#[test]
+fn blocks_mermaid_id() -> Result<(), Box<dyn Error>> {
+	assert_eq!(mermaid_id("agent2"), "agent2");
+	assert_eq!(mermaid_id("web-server"), "web_2d_server");
+	assert_eq!(mermaid_id("web_server"), "web_5f_server");
+	assert_ne!(mermaid_id("a-b_c"), mermaid_id("a_b-c"));
+
+	Ok(())
+}
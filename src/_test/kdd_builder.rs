@@ -0,0 +1,73 @@
+use super::{sort_builders, Builder};
+use crate::kdd::error::KddError;
+use std::error::Error;
+use yaml_rust::YamlLoader;
+
+fn load_builders(yaml_txt: &str) -> Vec<Builder> {
+	let yaml = YamlLoader::load_from_str(yaml_txt).unwrap().remove(0);
+	yaml.as_vec().unwrap().iter().filter_map(Builder::from_yaml).collect()
+}
+
+#[test]
+fn builder_sort_after_before() -> Result<(), Box<dyn Error>> {
+	let builders = load_builders(
+		r#"
+- name: tsc
+  when_file: ./tsconfig.json
+  after: [npm_install]
+  exec:
+    cmd: node_modules/.bin/tsc
+- name: npm_install
+  when_file: ./package.json
+  exec:
+    cmd: npm
+- name: codegen
+  when_file: ./codegen.json
+  before: [npm_install, tsc]
+  exec:
+    cmd: ./codegen.sh
+- name: pcss
+  when_file: ./pcss.config.js
+  exec:
+    cmd: node_modules/.bin/pcss
+"#,
+	);
+
+	let sorted = sort_builders("web", builders.iter().collect())?;
+	let names: Vec<&str> = sorted.iter().map(|b| b.name.as_str()).collect();
+	assert_eq!(vec!["codegen", "npm_install", "tsc", "pcss"], names);
+
+	Ok(())
+}
+
+#[test]
+fn builder_sort_cycle() -> Result<(), Box<dyn Error>> {
+	let builders = load_builders(
+		r#"
+- name: a
+  when_file: ./a
+  after: [b]
+  exec:
+    cmd: a
+- name: b
+  when_file: ./b
+  after: [a]
+  exec:
+    cmd: b
+- name: c
+  when_file: ./c
+  exec:
+    cmd: c
+"#,
+	);
+
+	match sort_builders("web", builders.iter().collect()) {
+		Err(KddError::BuildersCycle(block, names)) => {
+			assert_eq!("web", block);
+			assert_eq!("a, b", names);
+		}
+		other => panic!("should be a BuildersCycle error, but was {:?}", other.map(|v| v.len())),
+	}
+
+	Ok(())
+}
@@ -0,0 +1,40 @@
+use super::{is_valid_image_tag, pushed_digest};
+use std::error::Error;
+
+#[test]
+fn docker_pushed_digest() -> Result<(), Box<dyn Error>> {
+	let uris = vec!["reg/cstar-agent:1.0.0".to_string(), "reg/cstar-agent:latest".to_string()];
+	let remote = |_: &str| Some("sha256:pushed".to_string());
+	let digest = || Some("sha256:pushed".to_string());
+
+	// same local build as the realm tag, and in the registry, nothing to push
+	assert_eq!(pushed_digest(Some("sha256:a"), Some("sha256:a"), digest(), &uris, remote), digest());
+
+	// rebuilt image, stale realm tag (still on the pushed image), push
+	assert_eq!(pushed_digest(Some("sha256:b"), Some("sha256:a"), digest(), &uris, remote), None);
+
+	// never tagged for the realm, push
+	assert_eq!(pushed_digest(Some("sha256:b"), None, None, &uris, remote), None);
+
+	// one of the tags not (or another image) in the registry, push
+	let remote_partial = |uri: &str| uri.ends_with(":1.0.0").then(|| "sha256:pushed".to_string());
+	assert_eq!(pushed_digest(Some("sha256:a"), Some("sha256:a"), digest(), &uris, remote_partial), None);
+
+	Ok(())
+}
+
+#[test]
+fn docker_is_valid_image_tag() -> Result<(), Box<dyn Error>> {
+	assert!(is_valid_image_tag("1.2.0"));
+	assert!(is_valid_image_tag("dev-3f2a9c1"));
+	assert!(is_valid_image_tag("latest"));
+
+	// empty or dangling (e.g., `dev-{{git_sha}}` without the sha)
+	assert!(!is_valid_image_tag(""));
+	assert!(!is_valid_image_tag("dev-"));
+	assert!(!is_valid_image_tag("1.2."));
+	assert!(!is_valid_image_tag("-dev"));
+	assert!(!is_valid_image_tag("dev 1"));
+
+	Ok(())
+}
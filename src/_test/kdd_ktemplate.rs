@@ -0,0 +1,17 @@
+use super::upgrade_image_keys;
+use std::error::Error;
+
+#[test]
+fn ktemplate_upgrade_image_keys() -> Result<(), Box<dyn Error>> {
+	let content = "image: {{images.web-server.uri_pinned}}\ndigest: {{ images.agent.digest }}\nuri: {{images.agent.uri}}";
+	assert_eq!(
+		upgrade_image_keys(content),
+		"image: {{images_pinned.web-server}}\ndigest: {{ image_digests.agent }}\nuri: {{images.agent}}"
+	);
+
+	// current keys and plain text untouched
+	let content = "image: {{images.agent}} {{images_pinned.agent}}\n# images.agent.digest";
+	assert_eq!(upgrade_image_keys(content), content);
+
+	Ok(())
+}
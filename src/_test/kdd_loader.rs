@@ -1,5 +1,7 @@
+use std::collections::HashMap;
 use std::error::Error;
 
+use super::unescaped_run_time_vars;
 use crate::test_utils::*;
 
 const APP_1_BLOCK_NAMES: [&str; 12] = [
@@ -73,3 +75,15 @@ fn loader_prod_realm() -> Result<(), Box<dyn Error>> {
 
 	Ok(())
 }
+
+#[test]
+fn loader_unescaped_run_time_vars() -> Result<(), Box<dyn Error>> {
+	let txt = "args: ['pushed {{blocks}} to \\{{realm}}']\nenv:\n  DIR: '{{ block_dir }}'\n  REALM: '{{realm}}'\n  SYS: '{{system}}'\n";
+	assert_eq!(unescaped_run_time_vars(txt, &HashMap::new()), vec!["blocks", "block_dir", "realm"]);
+
+	// a root var with the same name is rendered at load time
+	let vars: HashMap<String, String> = [("blocks".to_string(), "a,b".to_string())].into_iter().collect();
+	assert_eq!(unescaped_run_time_vars("{{blocks}} \\{{realm}}", &vars), Vec::<&str>::new());
+
+	Ok(())
+}
@@ -0,0 +1,58 @@
+use super::{parse_timestamp, prune_tags, ImageTag, PruneOptions};
+use std::collections::HashSet;
+use std::error::Error;
+use std::time::Duration;
+
+#[test]
+fn prune_parse_timestamp() -> Result<(), Box<dyn Error>> {
+	assert_eq!(parse_timestamp("2024-05-01T10:00:00Z"), Some(1714557600));
+	assert_eq!(parse_timestamp("2024-05-01T10:00:00.123456Z"), Some(1714557600));
+	assert_eq!(parse_timestamp("2024-05-01T02:00:00-08:00"), Some(1714557600));
+	assert_eq!(parse_timestamp("2024-05-01 10:00:00 +0000 UTC"), Some(1714557600));
+	assert_eq!(parse_timestamp("1714557600.0"), Some(1714557600));
+	assert_eq!(parse_timestamp("not a date"), None);
+
+	Ok(())
+}
+
+#[test]
+fn prune_prune_tags() -> Result<(), Box<dyn Error>> {
+	let day = 86400;
+	let tag = |tag: &str, days: u64, digest: &str| ImageTag {
+		tag: tag.to_string(),
+		created: Some(1714557600 + days * day),
+		digest: Some(digest.to_string()),
+	};
+	// 1.0.0 (oldest) to 1.0.5, latest on the 1.0.5 image, 1.0.1 deployed
+	let tags = vec![
+		tag("1.0.0", 0, "sha256:a0"),
+		tag("1.0.1", 1, "sha256:a1"),
+		tag("1.0.2", 2, "sha256:a2"),
+		tag("1.0.3", 3, "sha256:a3"),
+		tag("1.0.4", 4, "sha256:a4"),
+		tag("1.0.5", 5, "sha256:a5"),
+		tag("latest", 5, "sha256:a5"),
+		tag("1.0.2-rc", 2, "sha256:a5"),
+	];
+	let protected: HashSet<String> = ["1.0.4".to_string()].into_iter().collect();
+	let rendered = vec!["image: localhost:5000/cstar-agent:1.0.1-beta\nimage: localhost:5000/cstar-agent:1.0.1\n".to_string()];
+	let options = PruneOptions {
+		keep: Some(2),
+		older_than: None,
+	};
+
+	let to_remove = prune_tags("localhost:5000/cstar-agent", &tags, &protected, &rendered, &options);
+	let mut to_remove: Vec<&str> = to_remove.iter().map(|t| t.tag.as_str()).collect();
+	to_remove.sort_unstable();
+	// 1.0.1 rendered, 1.0.4 protected, 1.0.2-rc same image as latest
+	assert_eq!(to_remove, vec!["1.0.0", "1.0.2", "1.0.3"]);
+
+	// nothing is older than 100 years
+	let options = PruneOptions {
+		keep: Some(0),
+		older_than: Some(Duration::from_secs(100 * 365 * day)),
+	};
+	assert!(prune_tags("localhost:5000/cstar-agent", &tags, &protected, &rendered, &options).is_empty());
+
+	Ok(())
+}
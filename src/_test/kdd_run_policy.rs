@@ -0,0 +1,70 @@
+use super::{parse_duration, policy_error, RunPolicy};
+use crate::kdd::error::KddError;
+use std::error::Error;
+use std::time::Duration;
+use yaml_rust::YamlLoader;
+
+#[test]
+fn run_policy_parse_duration() -> Result<(), Box<dyn Error>> {
+	assert_eq!(parse_duration("300"), Some(Duration::from_secs(300)));
+	assert_eq!(parse_duration("300ms"), Some(Duration::from_millis(300)));
+	assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+	assert_eq!(parse_duration(" 5m "), Some(Duration::from_secs(300)));
+	assert_eq!(parse_duration("1h"), Some(Duration::from_secs(3600)));
+	assert_eq!(parse_duration("30d"), Some(Duration::from_secs(30 * 86400)));
+	assert_eq!(parse_duration("0"), Some(Duration::ZERO));
+
+	// bad input
+	assert_eq!(parse_duration(""), None);
+	assert_eq!(parse_duration("5x"), None);
+	assert_eq!(parse_duration("m"), None);
+	assert_eq!(parse_duration("-5s"), None);
+	assert_eq!(parse_duration("1.5h"), None);
+
+	// overflow
+	assert_eq!(parse_duration("999999999999999999d"), None);
+	assert_eq!(parse_duration("99999999999999999999"), None);
+
+	Ok(())
+}
+
+#[test]
+fn run_policy_from_yaml() -> Result<(), Box<dyn Error>> {
+	let yaml = &YamlLoader::load_from_str("timeout: 5m\nretries: 2")?[0];
+	let policy = RunPolicy::from_yaml(yaml)?;
+	assert_eq!(policy.timeout, Some(Duration::from_secs(300)));
+	assert_eq!(policy.retries, 2);
+
+	// as number of seconds, and no retries
+	let policy = RunPolicy::from_yaml(&YamlLoader::load_from_str("timeout: 300")?[0])?;
+	assert_eq!(policy.timeout, Some(Duration::from_secs(300)));
+	assert_eq!(policy.retries, 0);
+
+	// nothing
+	let policy = RunPolicy::from_yaml(&YamlLoader::load_from_str("other: 1")?[0])?;
+	assert_eq!(policy.timeout, None);
+	assert_eq!(policy.retries, 0);
+
+	// invalid
+	for content in ["timeout: 5x", "timeout: 0", "retries: -1", "retries: two"] {
+		let res = RunPolicy::from_yaml(&YamlLoader::load_from_str(content)?[0]);
+		assert!(matches!(res, Err(KddError::InvalidRunPolicy(..))), "{} should be invalid", content);
+	}
+
+	Ok(())
+}
+
+#[test]
+fn run_policy_policy_error() -> Result<(), Box<dyn Error>> {
+	let ex = policy_error(KddError::ExecTimeout("docker push".to_string(), 60), KddError::DpushFailed);
+	assert!(matches!(ex, KddError::ExecTimeout(..)));
+	let ex = policy_error(
+		KddError::RetriesExhausted("docker push".to_string(), 3, "x".to_string()),
+		KddError::DpushFailed,
+	);
+	assert!(matches!(ex, KddError::RetriesExhausted(..)));
+	let ex = policy_error(KddError::ExecFailed("docker push".to_string(), "1".to_string()), KddError::DpushFailed);
+	assert!(matches!(ex, KddError::DpushFailed(..)));
+
+	Ok(())
+}
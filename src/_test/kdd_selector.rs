@@ -0,0 +1,43 @@
+use super::{levenshtein, select_names, Selectable};
+use std::error::Error;
+
+#[test]
+fn selector_select_names() -> Result<(), Box<dyn Error>> {
+	let backend = vec!["backend".to_string()];
+	let node = vec!["backend".to_string(), "node".to_string()];
+	let items = vec![
+		Selectable {
+			name: "web-server",
+			tags: &node,
+		},
+		Selectable {
+			name: "agent",
+			tags: &backend,
+		},
+		Selectable {
+			name: "agent2",
+			tags: &backend,
+		},
+		Selectable { name: "admin", tags: &[] },
+	];
+
+	assert_eq!(select_names("block", &items, &["agent", "admin"])?, vec!["agent", "admin"]);
+	assert_eq!(select_names("block", &items, &["@backend", "!agent2"])?, vec!["web-server", "agent"]);
+	assert_eq!(select_names("block", &items, &["agent*"])?, vec!["agent", "agent2"]);
+	assert_eq!(select_names("block", &items, &["!@backend"])?, vec!["admin"]);
+
+	// unmatched, with suggestions
+	let err = select_names("block", &items, &["agnet"]).err().ok_or("should fail")?;
+	assert_eq!(err.to_string(), "No block matching 'agnet', did you mean agent?");
+	let err = select_names("block", &items, &["@nod"]).err().ok_or("should fail")?;
+	assert_eq!(err.to_string(), "No block matching '@nod', did you mean @node?");
+
+	Ok(())
+}
+
+#[test]
+fn selector_levenshtein() {
+	assert_eq!(levenshtein("web-server", "web-server"), 0);
+	assert_eq!(levenshtein("agnet", "agent"), 2);
+	assert_eq!(levenshtein("", "abc"), 3);
+}
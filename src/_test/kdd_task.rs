@@ -0,0 +1,77 @@
+use super::{tasks_in_order, Task};
+use crate::kdd::error::KddError;
+use std::error::Error;
+use yaml_rust::YamlLoader;
+
+fn load_tasks(yaml_txt: &str) -> Vec<Task> {
+	let yaml = YamlLoader::load_from_str(yaml_txt).unwrap().remove(0);
+	yaml.as_vec().unwrap().iter().filter_map(Task::from_yaml).collect()
+}
+
+#[test]
+fn task_order_dependencies() -> Result<(), Box<dyn Error>> {
+	let tasks = load_tasks(
+		r#"
+- name: seed
+  dependencies: [migrate, fixtures]
+  exec:
+    cmd: psql
+- name: migrate
+  dependencies: [db-up]
+  exec:
+    cmd: ./migrate.sh
+- name: fixtures
+  dependencies: [db-up]
+  exec:
+    cmd: ./fixtures.sh
+- name: db-up
+  exec:
+    cmd: docker
+- name: other
+  exec:
+    cmd: other
+"#,
+	);
+
+	// db-up is a shared dependency (run once, before both)
+	let ordered = tasks_in_order(&tasks, "seed")?;
+	let names: Vec<&str> = ordered.iter().map(|t| t.name.as_str()).collect();
+	assert_eq!(vec!["db-up", "migrate", "fixtures", "seed"], names);
+
+	let ordered = tasks_in_order(&tasks, "other")?;
+	assert_eq!(1, ordered.len());
+
+	match tasks_in_order(&tasks, "nope") {
+		Err(KddError::TaskUnknown(name)) => assert_eq!("nope", name),
+		other => panic!("should be a TaskUnknown error, but was {:?}", other.map(|v| v.len())),
+	}
+
+	Ok(())
+}
+
+#[test]
+fn task_order_cycle() -> Result<(), Box<dyn Error>> {
+	let tasks = load_tasks(
+		r#"
+- name: a
+  dependencies: [b]
+  exec:
+    cmd: a
+- name: b
+  dependencies: [c]
+  exec:
+    cmd: b
+- name: c
+  dependencies: [a]
+  exec:
+    cmd: c
+"#,
+	);
+
+	match tasks_in_order(&tasks, "a") {
+		Err(KddError::TasksCycle(names)) => assert_eq!("a > b > c > a", names),
+		other => panic!("should be a TasksCycle error, but was {:?}", other.map(|v| v.len())),
+	}
+
+	Ok(())
+}
@@ -0,0 +1,37 @@
+use super::{glob_to_regex, WatchConfig};
+use std::error::Error;
+use yaml_rust::YamlLoader;
+
+#[test]
+fn watcher_glob_to_regex() -> Result<(), Box<dyn Error>> {
+	let rx = glob_to_regex("dist/**").ok_or("invalid glob")?;
+	assert!(rx.is_match("dist/index.js"));
+	assert!(rx.is_match("web/dist/js/index.js"));
+	assert!(!rx.is_match("src/dist.ts"));
+
+	let rx = glob_to_regex("*.log").ok_or("invalid glob")?;
+	assert!(rx.is_match("out.log"));
+	assert!(rx.is_match("logs/out.log"));
+	assert!(!rx.is_match("out.log.ts"));
+
+	let rx = glob_to_regex("/src/**/gen-*.ts").ok_or("invalid glob")?;
+	assert!(rx.is_match("src/gen-a.ts"));
+	assert!(rx.is_match("src/api/gen-b.ts"));
+	assert!(!rx.is_match("other/src/gen-a.ts"));
+
+	Ok(())
+}
+
+#[test]
+fn watcher_config_ignore() -> Result<(), Box<dyn Error>> {
+	let yaml = YamlLoader::load_from_str("debounce: 500ms\nignore: [dist, '*.tmp']")?.remove(0);
+	let config = WatchConfig::from_yaml(&yaml)?;
+
+	assert_eq!(config.debounce.as_millis(), 500);
+	assert!(config.is_ignored("node_modules/lodash/index.js")); // default
+	assert!(config.is_ignored("dist/main.js"));
+	assert!(config.is_ignored("src/a.tmp"));
+	assert!(!config.is_ignored("src/main.ts"));
+
+	Ok(())
+}
@@ -5,10 +5,18 @@ pub fn cmd_app() -> Command<'static> {
 		.version(&crate_version!()[..])
 		.about("Kubernetes Driven Development and Deployment")
 		.arg(arg_root_dir())
+		.arg(arg_dry_run())
 		.subcommand(sub_build())
 		.subcommand(sub_watch())
+		.subcommand(sub_dev())
+		.subcommand(sub_blocks())
 		.subcommand(sub_dbuild())
 		.subcommand(sub_dpush())
+		.subcommand(sub_dprune())
+		.subcommand(sub_bundle())
+		.subcommand(sub_clean())
+		.subcommand(sub_registry())
+		.subcommand(sub_run())
 		.subcommand(sub_realm())
 		.subcommand(sub_ktemplate())
 		.subcommand(sub_kaction(("kapply", "ka")))
@@ -25,6 +33,8 @@ fn sub_build() -> Command<'static> {
 	Command::new("build")
 		.about("Build one or more block")
 		.arg(Arg::new("blocks").help("Comma delimited block names (no space)"))
+		.arg(arg_explain())
+		.arg(arg_report())
 		.arg(arg_root_dir())
 }
 
@@ -35,18 +45,169 @@ fn sub_watch() -> Command<'static> {
 		.arg(arg_root_dir())
 }
 
+fn sub_dev() -> Command<'static> {
+	Command::new("dev")
+		.about("Dev loop on the current realm: on block change, build, docker build, push, kapply, and tail the block logs (Ctrl-C to stop)")
+		.arg(Arg::new("blocks").help("Comma delimited block names (no space)"))
+		.arg(arg_root_dir())
+}
+
+fn sub_blocks() -> Command<'static> {
+	Command::new("blocks")
+		.about("List the blocks with their dir, dependencies, builders, and image (for the current realm)")
+		.arg(Arg::new("blocks").help("Comma delimited block names or selectors (no space)"))
+		.arg(
+			Arg::new("graph")
+				.long("graph")
+				.takes_value(true)
+				.possible_values(["dot", "mermaid"])
+				.help("Print the blocks dependency graph (dot or mermaid)"),
+		)
+		.arg(
+			Arg::new("json")
+				.long("json")
+				.takes_value(false)
+				.help("Print the blocks as json"),
+		)
+		.arg(arg_root_dir())
+}
+
 fn sub_dbuild() -> Command<'static> {
 	Command::new("dbuild")
 		.about("Build and docker build one or more block")
 		.arg(Arg::new("blocks").help("Comma delimited block names (no space)"))
+		.arg(arg_explain())
+		.arg(arg_report())
 		.arg(arg_root_dir())
 }
 
 fn sub_dpush() -> Command<'static> {
 	Command::new("dpush")
-		.about("Docker push one or more block to a realm")
+		.about("Docker push one or more block to a realm (skipping the ones with the same digest in the registry)")
+		.arg(Arg::new("blocks").help("Comma delimited block names (no space)"))
+		.arg(
+			Arg::new("jobs")
+				.short('j')
+				.long("jobs")
+				.takes_value(true)
+				.value_parser(clap::value_parser!(usize))
+				.help("Number of blocks pushed concurrently (default 1)"),
+		)
+		.arg(arg_report())
+		.arg(arg_root_dir())
+}
+
+fn sub_dprune() -> Command<'static> {
+	Command::new("dprune")
+		.about("Remove the old image tags of one or more block, locally and in the realm registry (keeping the deployed ones)")
+		.arg(Arg::new("blocks").help("Comma delimited block names (no space)"))
+		.arg(
+			Arg::new("keep")
+				.long("keep")
+				.takes_value(true)
+				.value_parser(clap::value_parser!(usize))
+				.help("Number of most recent tags to keep per image (default 5)"),
+		)
+		.arg(
+			Arg::new("older_than")
+				.long("older-than")
+				.takes_value(true)
+				.help("Only remove the tags older than this (e.g., 30d, 12h)"),
+		)
+		.arg(arg_root_dir())
+}
+
+fn sub_bundle() -> Command<'static> {
+	Command::new("bundle")
+		.about("Package the realm images and rendered k8s files into a tar file (for air-gapped sites), or load one")
+		.args_conflicts_with_subcommands(true)
+		.subcommand_negates_reqs(true)
+		.arg(Arg::new("realm").required(true).help("The realm of the images and k8s files"))
 		.arg(Arg::new("blocks").help("Comma delimited block names (no space)"))
+		.arg(
+			Arg::new("out")
+				.short('o')
+				.long("out")
+				.takes_value(true)
+				.required(true)
+				.help("The bundle tar file (e.g., out.tar)"),
+		)
+		.arg(arg_root_dir())
+		.subcommand(
+			Command::new("load")
+				.about("Load the bundle images, push them to the realm registry, and apply its k8s files in order")
+				.arg(Arg::new("file").required(true).help("The bundle tar file"))
+				.arg(
+					Arg::new("realm")
+						.long("realm")
+						.takes_value(true)
+						.required(true)
+						.help("The target realm (registry and kubernetes context)"),
+				)
+				.arg(arg_root_dir()),
+		)
+}
+
+fn sub_clean() -> Command<'static> {
+	Command::new("clean")
+		.about("Run the builders clean commands and remove the rendered k8s files (and optionally the docker images)")
+		.arg(Arg::new("blocks").help(
+			"Comma delimited block names (no space). With blocks, only their builders and images are cleaned (the rendered k8s files are kept)",
+		))
+		.arg(
+			Arg::new("images")
+				.long("images")
+				.takes_value(false)
+				.help("Also remove the local docker images of the blocks"),
+		)
+		.arg(arg_root_dir())
+}
+
+fn sub_registry() -> Command<'static> {
+	Command::new("registry")
+		.about("Manage the local registry container (e.g., localhost:5000)")
+		.arg(arg_root_dir())
+		.subcommand(
+			Command::new("start")
+				.about("Start the local registry container (created with a persistent volume if needed)")
+				.arg(arg_root_dir()),
+		)
+		.subcommand(
+			Command::new("stop")
+				.about("Stop the local registry container (the images are kept)")
+				.arg(arg_root_dir()),
+		)
+		.subcommand(
+			Command::new("status")
+				.about("Print the local registry container status and if it is reachable")
+				.arg(arg_root_dir()),
+		)
+		.subcommand(
+			Command::new("list")
+				.about("List the local registry repositories and their tags")
+				.arg(arg_root_dir()),
+		)
+}
+
+fn sub_run() -> Command<'static> {
+	Command::new("run")
+		.about("Run a task of the kdd.yaml `tasks:` section (after its dependencies)")
 		.arg(arg_root_dir())
+		.arg(Arg::new("task").help("The task name"))
+		.arg(
+			Arg::new("list")
+				.short('l')
+				.long("list")
+				.takes_value(false)
+				.help("List the available tasks"),
+		)
+		.arg(
+			Arg::new("args")
+				.multiple_values(true)
+				.allow_hyphen_values(true)
+				.help("Extra args appended to the task exec (e.g., `kdd run seed prod.sql`, `kdd run test -- --nocapture`)"),
+		)
+		.trailing_var_arg(true)
 }
 
 fn sub_realm() -> Command<'static> {
@@ -154,4 +315,27 @@ fn arg_root_dir() -> Arg<'static> {
 		.help("The root dir where the driving kdd.yaml reside")
 }
 
+fn arg_dry_run() -> Arg<'static> {
+	Arg::new("dry_run")
+		.long("dry-run")
+		.global(true)
+		.takes_value(false)
+		.help("Print the external commands (docker, kubectl, aws, builders) with their cwd and env, without executing them")
+}
+
+fn arg_explain() -> Arg<'static> {
+	Arg::new("explain")
+		.long("explain")
+		.takes_value(false)
+		.help("Print the blocks and builders in their execution order, without executing them")
+}
+
+fn arg_report() -> Arg<'static> {
+	Arg::new("report")
+		.long("report")
+		.takes_value(true)
+		.value_name("path.json")
+		.help("Write the build report (blocks, builders, images, digests, push results and durations) as json to this file")
+}
+
 // endregion: Common Args
@@ -5,7 +5,15 @@
 use self::argc::cmd_app;
 use crate::{
 	app_error::AppError,
-	kdd::{error::KddError, Kdd},
+	kdd::{
+		blocks::GraphFormat,
+		error::KddError,
+		hook::{HookAction, HookTarget},
+		prune::PruneOptions,
+		report::BuildReport,
+		Kdd,
+	},
+	utils::{runner::set_dry_run, supervisor::install_signal_handler},
 };
 use clap::ArgMatches;
 use std::path::Path;
@@ -14,6 +22,9 @@ mod argc;
 
 pub fn cmd_run() -> Result<(), AppError> {
 	let app = cmd_app().get_matches();
+
+	// forward SIGINT/SIGTERM to the spawned children, and kill the remaining ones when done
+	let _supervisor = install_signal_handler();
 	let root_dir = app
 		.value_of("root_dir")
 		.or_else(|| {
@@ -25,11 +36,22 @@ pub fn cmd_run() -> Result<(), AppError> {
 		})
 		.unwrap_or("./");
 
+	// global --dry-run (print the external commands, without executing them)
+	let dry_run = app.is_present("dry_run") || app.subcommand().map(|(_, sub)| sub.is_present("dry_run")).unwrap_or(false);
+	set_dry_run(dry_run);
+
 	match app.subcommand() {
 		Some(("build", sub_cmd)) => exec_build(root_dir, sub_cmd, false)?,
 		Some(("watch", sub_cmd)) => exec_watch(root_dir, sub_cmd)?,
+		Some(("dev", sub_cmd)) => exec_dev(root_dir, sub_cmd)?,
+		Some(("blocks", sub_cmd)) => exec_blocks(root_dir, sub_cmd)?,
 		Some(("dbuild", sub_cmd)) => exec_build(root_dir, sub_cmd, true)?,
 		Some(("dpush", sub_cmd)) => exec_dpush(root_dir, sub_cmd)?,
+		Some(("dprune", sub_cmd)) => exec_dprune(root_dir, sub_cmd)?,
+		Some(("bundle", sub_cmd)) => exec_bundle(root_dir, sub_cmd)?,
+		Some(("clean", sub_cmd)) => exec_clean(root_dir, sub_cmd)?,
+		Some(("registry", sub_cmd)) => exec_registry(root_dir, sub_cmd)?,
+		Some(("run", sub_cmd)) => exec_run(root_dir, sub_cmd)?,
 		Some(("realm", sub_cmd)) => exec_realm(root_dir, sub_cmd)?,
 		Some(("ktemplate", sub_cmd)) => exec_kaction("template", root_dir, sub_cmd)?,
 		Some(("kapply", sub_cmd)) => exec_kaction("apply", root_dir, sub_cmd)?,
@@ -56,7 +78,23 @@ fn exec_build(root_dir: &str, argc: &ArgMatches, docker_build: bool) -> Result<(
 	let blocks = argc.value_of("blocks").map(|v| v.split(",").into_iter().collect::<Vec<&str>>());
 	let blocks = blocks.as_ref().map(|v| &v[..]);
 
-	kdd.build(blocks, docker_build)?;
+	if argc.is_present("explain") {
+		kdd.build_explain(blocks, docker_build)?;
+	} else {
+		let command = if docker_build { "dbuild" } else { "build" };
+		let realm = kdd.current_realm().ok().flatten();
+		let mut report = BuildReport::new(command, realm.map(|r| r.name.as_str()));
+		let (blocks_to_build, _) = kdd.blocks_for_names(blocks, docker_build)?;
+		let target = HookTarget {
+			realm,
+			blocks: blocks_to_build.iter().map(|b| b.name.to_string()).collect(),
+			..Default::default()
+		};
+		let action = if docker_build { HookAction::DBuild } else { HookAction::Build };
+		let res = kdd.with_hooks(action, &target, || kdd.build(blocks, docker_build, &mut report));
+		end_report(&report, argc)?;
+		res?;
+	}
 
 	Ok(())
 }
@@ -71,6 +109,45 @@ fn exec_watch(root_dir: &str, argc: &ArgMatches) -> Result<(), AppError> {
 	Ok(())
 }
 
+fn exec_dev(root_dir: &str, argc: &ArgMatches) -> Result<(), AppError> {
+	let kdd = load_kdd(root_dir)?;
+
+	let blocks = split_names(argc.value_of("blocks"));
+	let blocks = blocks.as_ref().map(|v| &v[..]);
+
+	if let Some(realm) = kdd.current_realm()? {
+		kdd.dev(realm, blocks)?;
+	} else {
+		println!("Cannot run dev, no current realm");
+	}
+
+	Ok(())
+}
+
+fn exec_blocks(root_dir: &str, argc: &ArgMatches) -> Result<(), AppError> {
+	let kdd = load_kdd(root_dir)?;
+
+	let blocks = split_names(argc.value_of("blocks"));
+	let blocks = blocks.as_ref().map(|v| &v[..]);
+
+	let graph_format = match argc.value_of("graph") {
+		Some("dot") => Some(GraphFormat::Dot),
+		Some("mermaid") => Some(GraphFormat::Mermaid),
+		_ => None,
+	};
+
+	if let Some(format) = graph_format {
+		print!("{}", kdd.blocks_graph(blocks, format)?);
+	} else if argc.is_present("json") {
+		let json = kdd.blocks_json(blocks)?;
+		println!("{}", serde_json::to_string_pretty(&json).map_err(KddError::from)?);
+	} else {
+		kdd.print_blocks(blocks)?;
+	}
+
+	Ok(())
+}
+
 fn exec_dpush(root_dir: &str, argc: &ArgMatches) -> Result<(), AppError> {
 	let kdd = load_kdd(root_dir)?;
 
@@ -78,8 +155,81 @@ fn exec_dpush(root_dir: &str, argc: &ArgMatches) -> Result<(), AppError> {
 	let blocks = blocks.as_ref().map(|v| &v[..]);
 
 	let realm = kdd.current_realm()?.ok_or_else(|| KddError::DpushFailNoRealm)?;
+	let jobs = argc.get_one::<usize>("jobs").copied().unwrap_or(1);
+
+	let mut report = BuildReport::new("dpush", Some(&realm.name));
+	let (blocks_to_push, _) = kdd.blocks_for_names(blocks, true)?;
+	let target = HookTarget {
+		realm: Some(realm),
+		blocks: blocks_to_push.iter().map(|b| b.name.to_string()).collect(),
+		..Default::default()
+	};
+	let res = kdd.with_hooks(HookAction::DPush, &target, || kdd.d_push(realm, blocks, jobs, &mut report));
+	end_report(&report, argc)?;
+	res?;
 
-	kdd.d_push(realm, blocks)?;
+	Ok(())
+}
+
+fn exec_dprune(root_dir: &str, argc: &ArgMatches) -> Result<(), AppError> {
+	let kdd = load_kdd(root_dir)?;
+
+	let blocks = split_names(argc.value_of("blocks"));
+	let blocks = blocks.as_ref().map(|v| &v[..]);
+
+	// Note: without a realm, only the local images
+	let realm = kdd.current_realm().ok().flatten();
+	let options = PruneOptions::new(argc.get_one::<usize>("keep").copied(), argc.value_of("older_than"))?;
+	kdd.d_prune(realm, blocks, &options)?;
+
+	Ok(())
+}
+
+fn exec_bundle(root_dir: &str, argc: &ArgMatches) -> Result<(), AppError> {
+	let kdd = load_kdd(root_dir)?;
+
+	match argc.subcommand() {
+		Some(("load", sub_cmd)) => {
+			let file = sub_cmd.value_of("file").unwrap_or_default();
+			let realm = sub_cmd.value_of("realm").unwrap_or_default();
+			kdd.bundle_load(Path::new(file), realm)?;
+		}
+		_ => {
+			let blocks = split_names(argc.value_of("blocks"));
+			let blocks = blocks.as_ref().map(|v| &v[..]);
+			let realm = argc.value_of("realm").unwrap_or_default();
+			let out = argc.value_of("out").unwrap_or_default();
+			kdd.bundle_create(realm, blocks, Path::new(out))?;
+		}
+	}
+
+	Ok(())
+}
+
+fn exec_clean(root_dir: &str, argc: &ArgMatches) -> Result<(), AppError> {
+	let kdd = load_kdd(root_dir)?;
+
+	let blocks = split_names(argc.value_of("blocks"));
+	let blocks = blocks.as_ref().map(|v| &v[..]);
+
+	kdd.clean(blocks, argc.is_present("images"))?;
+
+	Ok(())
+}
+
+fn exec_run(root_dir: &str, argc: &ArgMatches) -> Result<(), AppError> {
+	let kdd = load_kdd(root_dir)?;
+
+	match argc.value_of("task") {
+		Some(task) if !argc.is_present("list") => {
+			let args = argc
+				.values_of("args")
+				.map(|v| v.into_iter().map(|d| d.to_string()).collect::<Vec<String>>())
+				.unwrap_or_default();
+			kdd.run_task(task, &args)?;
+		}
+		_ => kdd.print_tasks(),
+	}
 
 	Ok(())
 }
@@ -103,19 +253,29 @@ fn exec_realm(root_dir: &str, argc: &ArgMatches) -> Result<(), AppError> {
 fn exec_kaction(action: &str, root_dir: &str, argc: &ArgMatches) -> Result<(), AppError> {
 	let kdd = load_kdd(root_dir)?;
 	let realm = kdd.current_realm()?;
-	let names = split_names(argc.value_of("names"));
-	let names = names.as_ref().map(|v| &v[..]);
+	let selectors = split_names(argc.value_of("names"));
 
 	if let Some(realm) = realm {
+		// names can be selectors (e.g., `@backend`, `web*`, `!admin`)
+		let names = selectors.map(|selectors| kdd.select_k8s_names(realm, &selectors)).transpose()?;
+		let names: Option<Vec<&str>> = names.as_ref().map(|v| v.iter().map(|n| n.as_str()).collect());
+		let names = names.as_deref();
+
 		// if no names, get the names from the realm default_configurations if present
 		let config_names = realm.default_configurations();
 		let names = names.or_else(|| config_names.as_ref().map(|v| v.as_slice()));
 
+		let target = HookTarget {
+			realm: Some(realm),
+			blocks: names.map(|v| v.iter().map(|n| n.to_string()).collect()).unwrap_or_default(),
+			files: realm.k8s_files(names),
+		};
+
 		match action {
-			"apply" => kdd.k_apply(realm, names)?,
+			"apply" => kdd.with_hooks(HookAction::KApply, &target, || kdd.k_apply(realm, names))?,
 			"create" => kdd.k_create(realm, names)?,
-			"delete" => kdd.k_delete(realm, names)?,
-			"template" => kdd.k_templates(realm, names, true).map(|result| ())?,
+			"delete" => kdd.with_hooks(HookAction::KDelete, &target, || kdd.k_delete(realm, names))?,
+			"template" => kdd.with_hooks(HookAction::KTemplate, &target, || kdd.k_templates(realm, names, true).map(|_| ()))?,
 			_ => (),
 		}
 	} else {
@@ -125,6 +285,22 @@ fn exec_kaction(action: &str, root_dir: &str, argc: &ArgMatches) -> Result<(), A
 	Ok(())
 }
 
+fn exec_registry(root_dir: &str, argc: &ArgMatches) -> Result<(), AppError> {
+	let kdd = load_kdd(root_dir)?;
+	let realm = kdd.current_realm().ok().flatten();
+
+	match argc.subcommand() {
+		Some(("start", _)) => kdd.registry_start(realm)?,
+		Some(("stop", _)) => kdd.registry_stop(realm)?,
+		Some(("status", _)) => kdd.registry_status(realm)?,
+		Some(("list", _)) => kdd.registry_list(realm)?,
+		_ => {
+			println!("Available actions: start, stop, status, list");
+		}
+	}
+	Ok(())
+}
+
 fn exec_kctx(root_dir: &str, argc: &ArgMatches) -> Result<(), AppError> {
 	match argc.subcommand() {
 		Some(("list", _)) => exec_kctx_list(root_dir)?,
@@ -238,6 +414,15 @@ fn split_names(val: Option<&str>) -> Option<Vec<&str>> {
 	val.map(|v| v.split(",").into_iter().collect::<Vec<&str>>())
 }
 
+/// Print the build report summary, and write it as json if `--report` is set
+fn end_report(report: &BuildReport, argc: &ArgMatches) -> Result<(), AppError> {
+	report.print_summary();
+	if let Some(path) = argc.value_of("report") {
+		report.write_json(Path::new(path))?;
+	}
+	Ok(())
+}
+
 fn load_kdd(root_dir: &str) -> Result<Kdd, AppError> {
 	let dir = Path::new(root_dir).to_path_buf();
 	Ok(Kdd::from_dir(dir)?)
@@ -4,12 +4,16 @@
 
 use yaml_rust::Yaml;
 
+use super::docker_settings::DockerSettings;
+
 use crate::utils::yamls::{as_string, as_strings, as_yaml_map, remove_keys};
 
 const BLOCK_KEY_NAME: &str = "name";
 const BLOCK_KEY_DIR: &str = "dir";
 const BLOCK_KEY_DEP: &str = "dependencies";
-const BLOCK_KEYS: &[&str] = &[BLOCK_KEY_NAME, BLOCK_KEY_DIR, BLOCK_KEY_DEP];
+const BLOCK_KEY_TAGS: &str = "tags";
+const BLOCK_KEY_DOCKER: &str = "docker";
+const BLOCK_KEYS: &[&str] = &[BLOCK_KEY_NAME, BLOCK_KEY_DIR, BLOCK_KEY_DEP, BLOCK_KEY_TAGS, BLOCK_KEY_DOCKER];
 
 //// Block Struct
 #[derive(Debug, Default)]
@@ -17,6 +21,10 @@ pub struct Block {
 	pub name: String,
 	pub dir: Option<String>,
 	pub dependencies: Option<Vec<String>>,
+	/// For the block selection (e.g., `kdd dbuild @backend`)
+	pub tags: Option<Vec<String>>,
+	/// The block `docker:` build settings (override the realm and kdd.yaml ones)
+	pub docker: DockerSettings,
 	pub map: Option<Yaml>,
 }
 
@@ -36,6 +44,8 @@ impl Block {
 				name: name.to_string(),
 				dir: as_string(&yaml, BLOCK_KEY_DIR),
 				dependencies: as_strings(&yaml, BLOCK_KEY_DEP),
+				tags: as_strings(&yaml, BLOCK_KEY_TAGS),
+				docker: DockerSettings::from_yaml(&yaml[BLOCK_KEY_DOCKER]),
 				map: as_yaml_map(y_map),
 			})
 		}
@@ -0,0 +1,148 @@
+////////////////////////////////////
+// kdd::blocks - The blocks listing (`kdd blocks`), as table, json, or dependency graph (dot, mermaid)
+////
+
+use super::{error::KddError, Block, Kdd};
+use serde_json::{json, Value};
+use std::fmt::Write;
+
+/// The `kdd blocks --graph` formats
+#[derive(Debug, Clone, Copy)]
+pub enum GraphFormat {
+	Dot,
+	Mermaid,
+}
+
+/// The resolved information of a block (for display)
+struct BlockInfo<'a> {
+	block: &'a Block,
+	dir: String,
+	builders: Vec<&'a str>,
+	dockerfile: bool,
+	image_uri: Option<String>,
+}
+
+//// Kdd Blocks Methods
+impl Kdd {
+	/// Print the blocks with their dir, dependencies, builders, and image uri (for the current realm)
+	pub fn print_blocks(&self, names: Option<&[&str]>) -> Result<(), KddError> {
+		tr_print("BLOCK", "DIR", "DEPENDENCIES", "BUILDERS", "DOCKERFILE", "IMAGE");
+		for info in self.blocks_info(names)? {
+			let deps = info
+				.block
+				.dependencies
+				.as_ref()
+				.map(|v| v.join(","))
+				.unwrap_or_else(|| "-".to_string());
+			let builders = if info.builders.is_empty() {
+				"-".to_string()
+			} else {
+				info.builders.join(",")
+			};
+			let dockerfile = if info.dockerfile { "yes" } else { "no" };
+			let image_uri = info.image_uri.as_deref().unwrap_or("-");
+			tr_print(&info.block.name, &info.dir, &deps, &builders, dockerfile, image_uri);
+		}
+		Ok(())
+	}
+
+	/// Returns the blocks information as a json array
+	pub fn blocks_json(&self, names: Option<&[&str]>) -> Result<Value, KddError> {
+		let infos = self.blocks_info(names)?;
+		let blocks: Vec<Value> = infos
+			.iter()
+			.map(|info| {
+				json!({
+					"name": info.block.name,
+					"dir": info.dir,
+					"dependencies": info.block.dependencies.clone().unwrap_or_default(),
+					"tags": info.block.tags.clone().unwrap_or_default(),
+					"builders": info.builders,
+					"dockerfile": info.dockerfile,
+					"image_uri": info.image_uri,
+				})
+			})
+			.collect();
+		Ok(Value::Array(blocks))
+	}
+
+	/// Returns the blocks dependency graph (an edge from each block to each of its dependencies)
+	pub fn blocks_graph(&self, names: Option<&[&str]>, format: GraphFormat) -> Result<String, KddError> {
+		let (blocks, _) = self.blocks_for_names(names, false)?;
+
+		// Note: write! to a String cannot fail
+		let mut out = String::new();
+		match format {
+			GraphFormat::Dot => {
+				let _ = writeln!(out, "digraph \"{}\" {{", self.system);
+				for block in blocks.iter() {
+					let shape = if self.has_dockerfile(block) { "box3d" } else { "box" };
+					let _ = writeln!(out, "  \"{}\" [shape={}];", block.name, shape);
+				}
+				for block in blocks.iter() {
+					for dep in block.dependencies.iter().flatten() {
+						let _ = writeln!(out, "  \"{}\" -> \"{}\";", block.name, dep);
+					}
+				}
+				out.push_str("}\n");
+			}
+			GraphFormat::Mermaid => {
+				out.push_str("graph TD\n");
+				for block in blocks.iter() {
+					let _ = writeln!(out, "  {}[\"{}\"]", mermaid_id(&block.name), block.name);
+				}
+				for block in blocks.iter() {
+					for dep in block.dependencies.iter().flatten() {
+						let _ = writeln!(out, "  {} --> {}", mermaid_id(&block.name), mermaid_id(dep));
+					}
+				}
+			}
+		}
+
+		Ok(out)
+	}
+
+	fn blocks_info(&self, names: Option<&[&str]>) -> Result<Vec<BlockInfo<'_>>, KddError> {
+		let (blocks, _) = self.blocks_for_names(names, false)?;
+		let realm = self.current_realm().ok().flatten();
+
+		let mut infos = Vec::new();
+		for block in blocks {
+			let dockerfile = self.has_dockerfile(block);
+			infos.push(BlockInfo {
+				block,
+				dir: self.get_block_dir(block).to_string_lossy().to_string(),
+				builders: self.builders_for_block(block)?.iter().map(|b| b.name.as_str()).collect(),
+				dockerfile,
+				image_uri: dockerfile.then(|| self.image_uri(block, realm)).transpose()?,
+			});
+		}
+		Ok(infos)
+	}
+}
+
+// region:    Utils
+fn tr_print(block: &str, dir: &str, deps: &str, builders: &str, dockerfile: &str, image: &str) {
+	println!("{: <20}{: <28}{: <24}{: <28}{: <12}{}", block, dir, deps, builders, dockerfile, image);
+}
+
+/// The mermaid node id of a block name (only alphanumeric and `_`).
+/// The other chars, `_` included, are escaped as `_<hex>_` so that the ids do not collide (e.g., `web-server` and `web_server`)
+fn mermaid_id(name: &str) -> String {
+	name.chars()
+		.map(|c| {
+			if c.is_ascii_alphanumeric() {
+				c.to_string()
+			} else {
+				format!("_{:x}_", c as u32)
+			}
+		})
+		.collect()
+}
+// endregion: Utils
+
+// region:    Test
+#[cfg(test)]
+#[path = "../_test/kdd_blocks.rs"]
+mod tests;
+// endregion: Test
@@ -1,42 +1,58 @@
 use futures::future::join_all;
 use std::collections::{HashMap, HashSet};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 
 use crate::kdd::builder::RunOccurrence;
-
-use super::{builder::Builder, error::KddError, Block, Kdd};
+use crate::utils::supervisor::is_stopping;
+
+use super::{
+	builder::{sort_builders, Builder},
+	error::KddError,
+	hook::{HookAction, HookTarget},
+	proc_log::ProcLog,
+	report::{BuildReport, StepReport},
+	run_policy::retry_backoff,
+	selector::{select_names, Selectable},
+	Block, Kdd,
+};
+
+// a crashed watch builder which ran at least this long gets restarted with the first backoff again
+const WATCH_RESTART_RESET: Duration = Duration::from_secs(60);
 
 impl Kdd {
 	pub fn blocks_for_names(&self, names: Option<&[&str]>, docker_block: bool) -> Result<(Vec<&Block>, HashMap<&str, &Block>), KddError> {
 		let block_by_name: HashMap<&str, &Block> = self.blocks.iter().map(|b| (b.name.as_str(), b)).collect();
 
+		// names can be selectors (e.g., `@backend`, `web*`, `!admin`)
 		let mut blocks_to_build = match names {
 			Some(names) => {
-				let mut blocks: Vec<&Block> = Vec::new();
-				for name in names {
-					match block_by_name.get(name) {
-						Some(block) => blocks.push(block),
-						None => return Err(KddError::BlockUnknown(name.to_string())),
-					}
-				}
-				blocks
+				let items: Vec<Selectable> = self
+					.blocks
+					.iter()
+					.map(|b| Selectable {
+						name: &b.name,
+						tags: b.tags.as_deref().unwrap_or(&[]),
+					})
+					.collect();
+				select_names("block", &items, names)?
+					.into_iter()
+					.filter_map(|name| block_by_name.get(name).copied())
+					.collect()
 			}
 			None => self.blocks.iter().collect::<Vec<&Block>>(),
 		};
 
 		// if dbuild, make sure all docker file
 		if docker_block {
-			blocks_to_build = blocks_to_build
-				.into_iter()
-				.filter(|b| self.get_block_dir(b).join("Dockerfile").is_file())
-				.collect();
+			blocks_to_build.retain(|b| self.has_dockerfile(b));
 		}
 
 		Ok((blocks_to_build, block_by_name))
 	}
 
-	fn builders_for_block(&self, block: &Block) -> Vec<&Builder> {
+	/// Returns the builders for this block (per their when_file and replace), in their final execution order.
+	pub fn builders_for_block(&self, block: &Block) -> Result<Vec<&Builder>, KddError> {
 		let mut block_builders: Vec<&Builder> = Vec::new();
 		let mut replace_names: HashSet<&str> = HashSet::new();
 		for builder in self.builders.iter() {
@@ -56,23 +72,56 @@ impl Kdd {
 			.filter(|b| !replace_names.contains(b.name.as_str()))
 			.collect();
 
-		block_builders
+		sort_builders(&block.name, block_builders)
 	}
 
+	/// Watch the blocks. The builders with `watch_args` run in their own watch mode (e.g., `tsc -w`),
+	/// the others are re-run by kdd on block file changes (see watcher.rs).
 	#[tokio::main(flavor = "current_thread")]
 	pub async fn watch(&self, names: Option<&[&str]>) -> Result<(), KddError> {
 		let (blocks_to_build, _) = self.blocks_for_names(names, false)?;
 
 		let mut handles = vec![];
+		let mut has_kdd_watched = false;
 
 		for block in blocks_to_build.iter() {
-			for builder in self.builders_for_block(block).iter() {
+			for builder in self.builders_for_block(block)?.iter() {
+				if !builder.exec.has_watch_args() {
+					has_kdd_watched = true;
+					continue;
+				}
 				let block_dir = self.get_block_dir(&block);
 				let kdd_dir = self.dir.clone();
 				let exec = builder.exec.clone();
+				let log = ProcLog::new(self, &block.name, &builder.name)?;
 
+				let step = format!("{} watch", builder.name);
 				handles.push(tokio::spawn(async move {
-					let _ = exec.execute_and_wait(kdd_dir.as_path(), block_dir.as_path(), true).await;
+					// a watch process should not fail, so, restart it (with backoff) unless kdd is stopping
+					let mut attempt = 0;
+					loop {
+						let start = Instant::now();
+						let res = exec
+							.execute_and_wait(kdd_dir.as_path(), block_dir.as_path(), true, &log, None)
+							.await;
+						let ex = match res {
+							Ok(_) => break,
+							Err(_) if is_stopping() => break,
+							Err(ex) => ex,
+						};
+						if start.elapsed() > WATCH_RESTART_RESET {
+							attempt = 0;
+						}
+						let backoff = retry_backoff(attempt);
+						log.line(&format!(
+							"KDD WARNING - '{}' crashed. Cause: {}. Restarting in {}s",
+							step,
+							ex,
+							backoff.as_secs()
+						));
+						sleep(backoff).await;
+						attempt += 1;
+					}
 				}));
 
 				// give some time for each builder to get started (better console readability)
@@ -80,13 +129,30 @@ impl Kdd {
 			}
 		}
 
+		// Note: all selected blocks are watched, as a change can affect a dependent block builder
+		if has_kdd_watched {
+			self.watch_blocks(&blocks_to_build).await?;
+		}
+
 		join_all(handles).await;
 
 		Ok(())
 	}
 
 	#[tokio::main(flavor = "current_thread")]
-	pub async fn build(&self, names: Option<&[&str]>, docker_build: bool) -> Result<(), KddError> {
+	pub async fn build(&self, names: Option<&[&str]>, docker_build: bool, report: &mut BuildReport) -> Result<(), KddError> {
+		self.build_blocks(names, docker_build, true, report).await
+	}
+
+	/// The build (and docker build) of the blocks, for the async callers (e.g., `kdd dev`).
+	/// With `interactive`, offer to start the local registry when not reachable (otherwise, the local dpush is skipped).
+	pub async fn build_blocks(
+		&self,
+		names: Option<&[&str]>,
+		docker_build: bool,
+		interactive: bool,
+		report: &mut BuildReport,
+	) -> Result<(), KddError> {
 		let (blocks_to_build, block_by_name) = self.blocks_for_names(names, docker_build)?;
 
 		// we get the current realm to the automatic dpush when local (desktop)
@@ -98,6 +164,15 @@ impl Kdd {
 			_ => false,
 		};
 
+		// if the local registry is not reachable, offer to start it when interactive (otherwise, no push)
+		let side_load = current_realm.map(|r| r.is_side_load()).unwrap_or(false);
+		if push_to_local_registry && docker_build && !side_load && !self.registry_is_reachable(*current_realm) {
+			push_to_local_registry = interactive && self.registry_offer_start(*current_realm)?;
+			if !push_to_local_registry {
+				println!("Skip dpush to local registry (not reachable, see `kdd registry start`).");
+			}
+		}
+
 		// blocks built
 		let mut blocks_built: HashSet<String> = HashSet::new();
 
@@ -112,24 +187,39 @@ impl Kdd {
 			kdd: &Kdd,
 			mut blocks_built: HashSet<String>,
 			mut builders_executed: HashSet<String>,
-		) -> (HashSet<String>, HashSet<String>) {
+			report: &mut BuildReport,
+		) -> Result<(HashSet<String>, HashSet<String>), KddError> {
 			let block_dir = kdd.get_block_dir(block);
 
-			let builders = kdd.builders_for_block(block);
+			let builders = kdd.builders_for_block(block)?;
 			let has_builder = builders.len() > 0;
 
 			// run all the builders for this block
 			for (idx, builder) in builders.iter().enumerate() {
 				if builder.run == RunOccurrence::Session && builders_executed.contains(&builder.name) {
 					println!("- skipping builder '{}' (session builder already ran)", builder.name);
+					let step = StepReport::skipped(&builder.name, "session builder already ran");
+					report.block(&block.name).builders.push(step);
 					continue;
 				}
 				if idx == 0 {
 					println!("===  Executing Builders for '{}' ", block.name);
 				}
 				println!("--- builder - {} for [{}]", builder.name, block.name);
-				// ignore error, handled in the execute and wait
-				let _ = builder.exec.execute_and_wait(&kdd.dir, &block_dir, false).await;
+				let log = ProcLog::new(kdd, &block.name, &builder.name)?;
+				let start = Instant::now();
+				let policy = &builder.policy;
+				let res = policy
+					.run_async(&builder.name, &log, || {
+						builder.exec.execute_and_wait(&kdd.dir, &block_dir, false, &log, policy.timeout)
+					})
+					.await;
+				let step = StepReport::from_result(&builder.name, start, &res);
+				report.block(&block.name).builders.push(step);
+				// Note: a failed builder does not stop the build (the tail of the log was printed in the execute and wait)
+				if let Err(ex) = res {
+					println!("KDD WARNING - builder '{}' for [{}] failed. Cause: {}", builder.name, block.name, ex);
+				}
 				builders_executed.insert(builder.name.to_string());
 				println!();
 			}
@@ -140,7 +230,7 @@ impl Kdd {
 
 			// add it to the list
 			blocks_built.insert(block.name.to_string());
-			(blocks_built, builders_executed)
+			Ok((blocks_built, builders_executed))
 		}
 
 		for block in blocks_to_build {
@@ -153,7 +243,7 @@ impl Kdd {
 						match block_by_name.get(block_name.as_str()) {
 							Some(dep_block) => {
 								println!("======  Dependency '{}' for '{}' building... ", dep_block.name, block.name);
-								(blocks_built, builders_executed) = build_block(dep_block, &self, blocks_built, builders_executed).await;
+								(blocks_built, builders_executed) = build_block(dep_block, self, blocks_built, builders_executed, report).await?;
 								println!("====== /Dependency '{}' for '{}' DONE\n", dep_block.name, block.name);
 							}
 							None => {
@@ -164,16 +254,30 @@ impl Kdd {
 				}
 			}
 
-			(blocks_built, builders_executed) = build_block(block, &self, blocks_built, builders_executed).await;
+			(blocks_built, builders_executed) = build_block(block, self, blocks_built, builders_executed, report).await?;
 
 			if docker_build {
 				println!("======  Docker Build for '{}' ", block.name);
-				self.d_build_block(block)?;
+				let start = Instant::now();
+				let res = self.d_build_block(block, *current_realm);
+				let mut step = StepReport::from_result(&self.image_uri(block, None)?, start, &res);
+				if res.is_ok() {
+					step.digest = self.d_image_id(&step.name, *current_realm);
+				}
+				report.block(&block.name).image = Some(step);
+				res?;
 				println!("====== /Docker Build for '{}' DONE ", block.name);
 
 				if push_to_local_registry {
 					if let Some(realm) = current_realm {
-						match self.d_push(realm, Some(&[block.name.as_ref()])) {
+						// as `kdd dpush`, with its pre_/post_ hooks (a failing pre_dpush hook skips the push)
+						let target = HookTarget {
+							realm: Some(realm),
+							blocks: vec![block.name.to_string()],
+							..Default::default()
+						};
+						let push = || self.d_push(realm, Some(&[block.name.as_ref()]), 1, report);
+						match self.with_hooks_async(HookAction::DPush, &target, push).await {
 							Ok(_) => (),
 							Err(ex) => {
 								println!("WARNING dpush to local registry failed. Cause: {}", ex);
@@ -191,4 +295,47 @@ impl Kdd {
 
 		Ok(())
 	}
+
+	/// Print the blocks (with their dependencies) and builders in the order `build` would execute them, without executing anything.
+	pub fn build_explain(&self, names: Option<&[&str]>, docker_build: bool) -> Result<(), KddError> {
+		let (blocks_to_build, block_by_name) = self.blocks_for_names(names, docker_build)?;
+		let selected_names: HashSet<&str> = blocks_to_build.iter().map(|b| b.name.as_str()).collect();
+
+		// the blocks in build order (dependencies first, each block once)
+		let mut blocks: Vec<&Block> = Vec::new();
+		for block in blocks_to_build {
+			for dep_name in block.dependencies.iter().flatten() {
+				if let Some(dep_block) = block_by_name.get(dep_name.as_str()) {
+					if !blocks.iter().any(|b| b.name == dep_block.name) {
+						blocks.push(dep_block);
+					}
+				}
+			}
+			if !blocks.iter().any(|b| b.name == block.name) {
+				blocks.push(block);
+			}
+		}
+
+		let mut builders_executed: HashSet<&str> = HashSet::new();
+		for block in blocks {
+			println!("--- Block '{}' ", block.name);
+			let builders = self.builders_for_block(block)?;
+			if builders.is_empty() {
+				println!("    (no builders)");
+			}
+			for (idx, builder) in builders.iter().enumerate() {
+				if builder.run == RunOccurrence::Session && builders_executed.contains(builder.name.as_str()) {
+					println!("    {}. {} (skipped, session builder already ran)", idx + 1, builder.name);
+				} else {
+					println!("    {}. {}", idx + 1, builder.name);
+				}
+				builders_executed.insert(&builder.name);
+			}
+			if docker_build && selected_names.contains(block.name.as_str()) {
+				println!("    > docker build");
+			}
+		}
+
+		Ok(())
+	}
 }
@@ -2,14 +2,28 @@
 // kdev::builder - The the kdd Builder component and its Exec component
 ////
 
-use super::error::KddError;
+use super::{
+	error::KddError,
+	proc_log::{pipe_child, ProcLog},
+	run_policy::RunPolicy,
+};
 use crate::{
-	utils::path_to_string,
-	utils::yamls::{as_str, as_string, as_strings},
+	utils::runner::ExtCmd,
+	utils::supervisor::ChildGuard,
+	utils::{kill_process_group, path_to_string, set_process_group},
+	utils::yamls::{as_str, as_string, as_strings, to_string},
 };
 use pathdiff::diff_paths;
+use futures::future::join_all;
+use handlebars::Handlebars;
+use std::collections::HashMap;
 use std::path::Path;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::process::{Child, Command};
+use tokio::time;
+use tokio::task::JoinHandle;
 use yaml_rust::Yaml;
 
 //// Builder Struct
@@ -20,7 +34,15 @@ pub struct Builder {
 	/// Define if this should be ran once per session or per block
 	pub run: RunOccurrence,
 	pub replace: Option<String>,
+	/// Builder names this builder must run after (when present for the block)
+	pub after: Option<Vec<String>>,
+	/// Builder names this builder must run before (when present for the block)
+	pub before: Option<Vec<String>>,
+	/// The eventual timeout and retries (when running as part of a build, not in watch mode)
+	pub policy: RunPolicy,
 	pub exec: Exec,
+	/// The eventual exec to clean what this builder produces (for `kdd clean`)
+	pub clean: Option<Exec>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -36,7 +58,7 @@ impl Builder {
 			let exec = match Exec::from_yaml(&yaml["exec"]) {
 				Ok(exec) => exec,
 				Err(ex) => {
-					println!(
+					eprintln!(
 						"KDD PARSING WARNING - Builder {} does not have a value exec element. Cause: {}. Skipping",
 						name, ex
 					);
@@ -44,12 +66,37 @@ impl Builder {
 				}
 			};
 
+			let clean = match &yaml["clean"] {
+				Yaml::BadValue => None,
+				y_clean => match Exec::from_yaml(y_clean) {
+					Ok(clean) => Some(clean),
+					Err(ex) => {
+						eprintln!(
+							"KDD PARSING WARNING - Builder {} does not have a valid clean element. Cause: {}. Skipping",
+							name, ex
+						);
+						return None;
+					}
+				},
+			};
+
 			let when_file = as_string(yaml, "when_file");
 
 			let replace = as_string(yaml, "replace");
 
+			let after = as_strings(yaml, "after");
+			let before = as_strings(yaml, "before");
+
+			let policy = match RunPolicy::from_yaml(yaml) {
+				Ok(policy) => policy,
+				Err(ex) => {
+					println!("KDD ERROR PARSING KDD YAML. Builder {}. Cause: {}. Skipping", name, ex);
+					return None;
+				}
+			};
+
 			if when_file.is_none() {
-				println!(
+				eprintln!(
 					"KDD PARSING WARNING - Processor {} does not have an .when_file property. Will never get triggered",
 					name
 				);
@@ -71,8 +118,12 @@ impl Builder {
 				name: name.to_owned(),
 				run,
 				replace,
+				after,
+				before,
+				policy,
 				when_file,
 				exec,
+				clean,
 			})
 		} else {
 			None
@@ -80,6 +131,50 @@ impl Builder {
 	}
 }
 
+// region:    Builders Ordering
+/// Sort the builders of a block in a topological order given their `after` and `before` constraints.
+/// Constraints referencing a builder not in the list are ignored, and ties keep the declaration order.
+pub fn sort_builders<'a>(block_name: &str, builders: Vec<&'a Builder>) -> Result<Vec<&'a Builder>, KddError> {
+	let idx_by_name: HashMap<&str, usize> = builders.iter().enumerate().map(|(idx, b)| (b.name.as_str(), idx)).collect();
+
+	// -- build the edges (from must run before to)
+	let mut nexts: Vec<Vec<usize>> = vec![Vec::new(); builders.len()];
+	let mut in_degrees: Vec<usize> = vec![0; builders.len()];
+	for (idx, builder) in builders.iter().enumerate() {
+		let afters = builder.after.iter().flatten().filter_map(|name| idx_by_name.get(name.as_str())).map(|from| (*from, idx));
+		let befores = builder.before.iter().flatten().filter_map(|name| idx_by_name.get(name.as_str())).map(|to| (idx, *to));
+		for (from, to) in afters.chain(befores) {
+			if !nexts[from].contains(&to) {
+				nexts[from].push(to);
+				in_degrees[to] += 1;
+			}
+		}
+	}
+
+	// -- take the first declared builder without pending constraints, until none left
+	let mut done: Vec<bool> = vec![false; builders.len()];
+	let mut sorted: Vec<&Builder> = Vec::with_capacity(builders.len());
+	while sorted.len() < builders.len() {
+		let next = (0..builders.len()).find(|idx| !done[*idx] && in_degrees[*idx] == 0);
+		match next {
+			Some(idx) => {
+				done[idx] = true;
+				sorted.push(builders[idx]);
+				for to in nexts[idx].iter() {
+					in_degrees[*to] -= 1;
+				}
+			}
+			None => {
+				let names: Vec<&str> = (0..builders.len()).filter(|idx| !done[*idx]).map(|idx| builders[idx].name.as_str()).collect();
+				return Err(KddError::BuildersCycle(block_name.to_string(), names.join(", ")));
+			}
+		}
+	}
+
+	Ok(sorted)
+}
+// endregion: Builders Ordering
+
 // region:    Exec Component
 #[derive(Debug, Clone)]
 pub struct Exec {
@@ -93,6 +188,9 @@ pub struct Exec {
 
 	args: Vec<String>,
 	watch_args: Option<Vec<String>>,
+
+	/// Extra environment variables for the process (from the 'env' yaml map, or set by kdd)
+	env: Vec<(String, String)>,
 }
 
 #[derive(Debug, Clone)]
@@ -116,6 +214,15 @@ impl Cmd {
 			Cmd::Relative(val) => val,
 		}
 	}
+
+	/// Returns the same kind of Cmd with the name mapped
+	fn map(&self, f: impl FnOnce(&str) -> String) -> Cmd {
+		match self {
+			Cmd::Global(val) => Cmd::Global(f(val)),
+			Cmd::Base(val) => Cmd::Base(f(val)),
+			Cmd::Relative(val) => Cmd::Relative(f(val)),
+		}
+	}
 }
 
 //// Exec Builder(s)
@@ -146,27 +253,140 @@ impl Exec {
 
 		let args = as_strings(&y_exec, "args").unwrap_or_else(|| Vec::new());
 		let watch_args = as_strings(&y_exec, "watch_args");
+
+		// -- extract the eventual env (name/value map)
+		let env = y_exec["env"]
+			.as_hash()
+			.map(|env| {
+				env.iter()
+					.filter_map(|(name, val)| Some((to_string(name)?, to_string(val)?)))
+					.collect::<Vec<_>>()
+			})
+			.unwrap_or_default();
+
 		Ok(Exec {
 			cmd,
 			cwd,
 			args,
 			watch_args,
+			env,
+		})
+	}
+
+	/// Returns a new exec with its cmd, args, and env values rendered (handlebars) with these vars
+	pub fn render(&self, hbs: &Handlebars, vars: &HashMap<String, String>) -> Result<Exec, KddError> {
+		let render = |val: &str| -> Result<String, KddError> {
+			hbs.render_template(val, vars)
+				.map_err(|ex| KddError::ExecFailRender(self.cmd.name().to_string(), ex.to_string()))
+		};
+		let render_all = |vals: &[String]| vals.iter().map(|v| render(v)).collect::<Result<Vec<String>, KddError>>();
+
+		let cmd_name = render(self.cmd.name())?;
+		let watch_args = match &self.watch_args {
+			Some(watch_args) => Some(render_all(watch_args)?),
+			None => None,
+		};
+		let env = self
+			.env
+			.iter()
+			.map(|(name, val)| Ok((name.to_string(), render(val)?)))
+			.collect::<Result<Vec<(String, String)>, KddError>>()?;
+
+		Ok(Exec {
+			cmd: self.cmd.map(|_| cmd_name),
+			cwd: self.cwd.clone(),
+			args: render_all(&self.args)?,
+			watch_args,
+			env,
 		})
 	}
+
+	/// Returns a new exec with these environment variables added
+	pub fn with_env(&self, env: &[(String, String)]) -> Exec {
+		let mut exec = self.clone();
+		exec.env.extend(env.iter().cloned());
+		exec
+	}
+
+	/// Returns a new exec with these args appended (to the args and watch_args)
+	pub fn with_extra_args(&self, extra_args: &[String]) -> Exec {
+		let mut exec = self.clone();
+		exec.args.extend(extra_args.iter().cloned());
+		if let Some(watch_args) = exec.watch_args.as_mut() {
+			watch_args.extend(extra_args.iter().cloned());
+		}
+		exec
+	}
 }
 
 //// Exec Public Methods
 impl Exec {
-	pub async fn execute_and_wait(&self, kdd_dir: &Path, block_dir: &Path, watch: bool) -> Result<(), KddError> {
-		let mut proc = self.execute(kdd_dir, block_dir, watch)?;
+	/// Returns true if this exec has its own watch mode (i.e., `watch_args`)
+	pub fn has_watch_args(&self) -> bool {
+		self.watch_args.is_some()
+	}
+
+	/// Execute and wait for the process. If the timeout is exceeded, its process tree gets killed.
+	pub async fn execute_and_wait(
+		&self,
+		kdd_dir: &Path,
+		block_dir: &Path,
+		watch: bool,
+		log: &Arc<ProcLog>,
+		timeout: Option<Duration>,
+	) -> Result<(), KddError> {
+		let ext_cmd = self.ext_cmd(kdd_dir, block_dir, watch)?;
+		if ext_cmd.dry_run() {
+			return Ok(());
+		}
+		let (mut proc, guard, readers) = self.spawn(&ext_cmd, log)?;
+
+		let status = match timeout {
+			None => proc.wait().await,
+			Some(timeout) => match time::timeout(timeout, proc.wait()).await {
+				Ok(status) => status,
+				Err(_) => {
+					if let Some(pid) = proc.id() {
+						kill_process_group(pid);
+					}
+					let _ = proc.wait().await;
+					guard.done();
+					join_all(readers).await;
+					log.print_tail();
+					return Err(KddError::ExecTimeout(self.cmd.name().to_string(), timeout.as_secs()));
+				}
+			},
+		};
+		guard.done();
+		// make sure all of the output lines got logged
+		join_all(readers).await;
 
-		match proc.wait().await {
-			Ok(_) => Ok(()),
+		match status {
+			Ok(status) if status.success() => Ok(()),
+			Ok(status) => {
+				log.print_tail();
+				Err(KddError::ExecFailed(self.cmd.name().to_string(), status.to_string()))
+			}
 			Err(ex) => Err(KddError::CannotExecute(ex.to_string())),
 		}
 	}
 
-	pub fn execute(&self, kdd_dir: &Path, block_dir: &Path, watch: bool) -> Result<Child, KddError> {
+	/// Spawn the exec process, with its stdout/stderr piped to the log.
+	/// Returns the child, its supervisor guard (kills the process group on drop, unless done),
+	/// and its output readers (done once the child closed its outputs)
+	pub fn execute(
+		&self,
+		kdd_dir: &Path,
+		block_dir: &Path,
+		watch: bool,
+		log: &Arc<ProcLog>,
+	) -> Result<(Child, ChildGuard, Vec<JoinHandle<()>>), KddError> {
+		let ext_cmd = self.ext_cmd(kdd_dir, block_dir, watch)?;
+		self.spawn(&ext_cmd, log)
+	}
+
+	/// Returns the external command (cmd, args, cwd, env) of this exec
+	pub fn ext_cmd(&self, kdd_dir: &Path, block_dir: &Path, watch: bool) -> Result<ExtCmd, KddError> {
 		let cwd = match self.cwd {
 			Cwd::Block => block_dir,
 			Cwd::Base => kdd_dir,
@@ -178,10 +398,9 @@ impl Exec {
 			Cmd::Relative(val) => val.to_string(),
 			// e.g., node_modules/.bin/tsc (those need to be prefix to point back to base dir)
 			Cmd::Base(val) => {
-				// TODO: Needs to handle those unwrap eventually
-				let diff = diff_paths(kdd_dir, cwd).unwrap();
-				let path = diff.join(val);
-				path_to_string(&path).unwrap()
+				let diff = diff_paths(kdd_dir, cwd)
+					.ok_or_else(|| KddError::CannotExecute(format!("no path from {} to {}", cwd.display(), kdd_dir.display())))?;
+				path_to_string(&diff.join(val))?
 			}
 		};
 
@@ -191,20 +410,36 @@ impl Exec {
 			_ => &self.args[..],
 		};
 
-		// build proc
-		let mut proc = Command::new(&cmd);
-		proc.current_dir(&cwd);
-		proc.args(args);
+		Ok(ExtCmd::new(&cmd, args).cwd(Some(cwd)).env(&self.env))
+	}
+
+	fn spawn(&self, ext_cmd: &ExtCmd, log: &Arc<ProcLog>) -> Result<(Child, ChildGuard, Vec<JoinHandle<()>>), KddError> {
+		// build proc (in its own process group, so that the whole tree can be killed)
+		let mut proc = ext_cmd.command();
+		proc.stdout(Stdio::piped()).stderr(Stdio::piped());
+		set_process_group(&mut proc);
+		let mut proc = Command::from(proc);
 
 		// execute
-		println!("> executing: {} {} (at cwd: {})  ", cmd, args.join(" "), cwd.to_string_lossy(),);
+		let cwd = ext_cmd.cwd.as_deref().map(|cwd| cwd.to_string_lossy()).unwrap_or_default();
+		log.line(&format!("> executing: {} (at cwd: {})  ", ext_cmd.cmd_line(), cwd));
 		match proc.spawn() {
-			Ok(proc) => Ok(proc),
+			Ok(mut proc) => {
+				let guard = ChildGuard::track(proc.id().unwrap_or_default());
+				let readers = pipe_child(&mut proc, log);
+				Ok((proc, guard, readers))
+			}
 			Err(ex) => {
-				println!("  ERROR - Fail to execute. Cause: {}", ex);
+				log.line(&format!("ERROR - Fail to execute. Cause: {}", ex));
 				Err(KddError::CannotExecute(ex.to_string()))
 			}
 		}
 	}
 }
 // endregion: Exec Component
+
+// region:    Tests
+#[cfg(test)]
+#[path = "../_test/kdd_builder.rs"]
+mod tests;
+// endregion: Tests
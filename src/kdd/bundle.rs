@@ -0,0 +1,239 @@
+////////////////////////////////////
+// kdd::bundle - The air-gapped bundle (`kdd bundle`), the realm images, rendered k8s files, and their bundle.json
+////
+
+use super::{docker::realm_image_uri, error::KddError, image_state::image_repo, realm::Realm, Kdd};
+use crate::utils::{exec_cmd_args, path_to_string, runner::is_dry_run, runner::ExtCmd};
+use serde_json::{json, Value};
+use std::env::current_dir;
+use std::fs::{copy, create_dir_all, read_to_string, remove_dir_all, write};
+use std::path::{Path, PathBuf};
+
+const BUNDLE_DIR: &str = "bundle";
+const BUNDLE_LOAD_DIR: &str = "_load";
+const BUNDLE_MANIFEST: &str = "bundle.json";
+const BUNDLE_IMAGES: &str = "images.tar";
+const BUNDLE_K8S_DIR: &str = "k8s";
+const BUNDLE_VERSION: u64 = 1;
+
+//// Kdd Bundle Methods
+impl Kdd {
+	/// Package into one tar file the realm images of the blocks (saved from the local builds), the realm rendered k8s files,
+	/// and a `bundle.json` describing them (images uris and digests, k8s files in their apply order)
+	pub fn bundle_create(&self, realm_name: &str, names: Option<&[&str]>, out_file: &Path) -> Result<(), KddError> {
+		let realm = self.realm_by_name(realm_name)?;
+		let engine = self.engine(Some(realm))?;
+		let (blocks, _) = self.blocks_for_names(names, true)?;
+
+		let stage_dir = self.local_dir().join(BUNDLE_DIR).join(&realm.name);
+		if stage_dir.exists() {
+			remove_dir_all(&stage_dir)?;
+		}
+		create_dir_all(stage_dir.join(BUNDLE_K8S_DIR))?;
+
+		// -- tag the local builds with the realm uris (as dpush)
+		let state = self.load_images_state(realm);
+		let (mut images, mut image_uris_all) = (Vec::new(), Vec::new());
+		for block in blocks.iter() {
+			let local_image_uri = self.image_uri(block, None)?;
+			if !is_dry_run() && self.d_image_id(&local_image_uri, Some(realm)).is_none() {
+				return Err(KddError::BundleImageNotFound(local_image_uri));
+			}
+			let image_uris = self.image_uris(block, Some(realm))?;
+			for image_uri in image_uris.iter().filter(|uri| **uri != local_image_uri) {
+				ExtCmd::new(engine.cmd(), &engine.tag_args(&local_image_uri, image_uri)).exec()?;
+			}
+			// the pushed digest, as in the rendered `{{images_pinned.<block>}}`
+			let digest = state
+				.get(&block.name)
+				.filter(|pushed| pushed["uri"].as_str() == Some(image_uris[0].as_str()))
+				.and_then(|pushed| pushed["digest"].as_str());
+			images.push(json!({
+				"block": block.name,
+				"image_name": self.image_name(block),
+				"uris": image_uris,
+				"digest": digest,
+			}));
+			image_uris_all.extend(image_uris);
+		}
+
+		// -- save the images (one archive)
+		if !image_uris_all.is_empty() {
+			let images_file = path_to_string(&stage_dir.join(BUNDLE_IMAGES))?;
+			let args = engine
+				.save_args(&images_file, &image_uris_all)
+				.ok_or_else(|| KddError::ContainerEngineNoArchive(engine.cmd().to_string()))?;
+			ExtCmd::new(engine.cmd(), &args).exec()?;
+		}
+
+		// -- the rendered k8s files (all of the realm ones, in their kapply order)
+		let mut k8s_files = Vec::new();
+		for file in self.k_templates(realm, None, false)? {
+			if let Some(file_name) = file.file_name().and_then(|n| n.to_str()) {
+				copy(&file, stage_dir.join(BUNDLE_K8S_DIR).join(file_name))?;
+				k8s_files.push(format!("{}/{}", BUNDLE_K8S_DIR, file_name));
+			}
+		}
+
+		let bundle = json!({
+			"version": BUNDLE_VERSION,
+			"system": self.system,
+			"realm": realm.name,
+			"image_tag": self.image_tag(Some(realm))?,
+			"images_file": BUNDLE_IMAGES,
+			"images": images,
+			"k8s_files": k8s_files,
+		});
+		write(stage_dir.join(BUNDLE_MANIFEST), serde_json::to_string_pretty(&bundle)?)?;
+
+		// -- the tar file (relative to the current dir)
+		let out_file = path_to_string(&abs_path(out_file)?)?;
+		ExtCmd::new("tar", &["-cf", &out_file, "-C", &path_to_string(&stage_dir)?, "."]).exec()?;
+		println!(
+			"Bundle {} created (realm {}, {} images, {} k8s files)",
+			out_file,
+			realm.name,
+			images.len(),
+			k8s_files.len()
+		);
+
+		Ok(())
+	}
+
+	/// Load a bundle into this realm: load its images, retag and push them to the realm registry (or side-load them),
+	/// and apply its k8s files in order (with the image uris of this realm)
+	pub fn bundle_load(&self, bundle_file: &Path, realm_name: &str) -> Result<(), KddError> {
+		let realm = self.realm_by_name(realm_name)?;
+		let engine = self.engine(Some(realm))?;
+		let bundle_name = bundle_file.to_string_lossy().to_string();
+
+		// -- extract the bundle (in dry-run as well, to read its bundle.json)
+		let load_dir = self.local_dir().join(BUNDLE_DIR).join(BUNDLE_LOAD_DIR);
+		if load_dir.exists() {
+			remove_dir_all(&load_dir)?;
+		}
+		create_dir_all(&load_dir)?;
+		let bundle_path = path_to_string(&abs_path(bundle_file)?)?;
+		ExtCmd::new("tar", &["-xf", &bundle_path, "-C", &path_to_string(&load_dir)?])
+			.query()
+			.exec()?;
+
+		let bundle = read_to_string(load_dir.join(BUNDLE_MANIFEST)).map_err(|ex| KddError::BundleInvalid(bundle_name.to_string(), ex.to_string()))?;
+		let bundle: Value = serde_json::from_str(&bundle).map_err(|ex| KddError::BundleInvalid(bundle_name.to_string(), ex.to_string()))?;
+		if bundle["version"].as_u64() != Some(BUNDLE_VERSION) {
+			return Err(KddError::BundleInvalid(
+				bundle_name,
+				format!("version {} not supported", bundle["version"]),
+			));
+		}
+		if bundle["system"].as_str() != Some(self.system.as_str()) {
+			println!(
+				"KDD WARNING - bundle system '{}' is not the kdd.yaml system '{}'",
+				bundle["system"].as_str().unwrap_or_default(),
+				self.system
+			);
+		}
+		let images = bundle["images"].as_array().cloned().unwrap_or_default();
+
+		// -- load the images
+		if let Some(images_file) = bundle["images_file"].as_str().filter(|_| !images.is_empty()) {
+			let images_file = path_to_string(&load_dir.join(images_file))?;
+			let args = engine
+				.load_args(&images_file)
+				.ok_or_else(|| KddError::ContainerEngineNoArchive(engine.cmd().to_string()))?;
+			ExtCmd::new(engine.cmd(), &args).exec()?;
+		}
+
+		// -- retag and push (or side-load) to the realm, and record the uri replacements for the k8s files
+		let local = realm.is_local_registry();
+		if !realm.is_side_load() && !local {
+			let names: Vec<&str> = images.iter().filter_map(|image| image["block"].as_str()).collect();
+			let blocks: Vec<_> = self.blocks.iter().filter(|b| names.contains(&b.name.as_str())).collect();
+			realm.provider().before_dpushes(&self.system, realm, &blocks)?;
+			realm.provider().docker_auth(realm, engine)?;
+		}
+		let mut replacements: Vec<(String, String)> = Vec::new();
+		for image in images.iter() {
+			let image_name = image["image_name"].as_str().unwrap_or_default();
+			let uris: Vec<&str> = image["uris"]
+				.as_array()
+				.into_iter()
+				.flatten()
+				.filter_map(|v| v.as_str())
+				.collect();
+			let Some(src_uri) = uris.first() else { continue };
+
+			let mut target_uris = Vec::new();
+			for uri in uris.iter() {
+				let tag = uri.rsplit_once(':').map(|(_, tag)| tag).unwrap_or_default();
+				let target_uri = realm_image_uri(image_name, tag, Some(realm));
+				if target_uri != *uri {
+					ExtCmd::new(engine.cmd(), &engine.tag_args(uri, &target_uri)).exec()?;
+				}
+				if realm.is_side_load() {
+					realm.provider().side_load_image(realm, &target_uri)?.exec()?;
+				} else {
+					ExtCmd::new(engine.cmd(), &engine.push_args(&target_uri, local))
+						.exec()
+						.map_err(|ex| KddError::DpushFailed(ex.to_string()))?;
+				}
+				target_uris.push(target_uri);
+			}
+
+			// Note: the digest pinned uris change with the registry (the pushed digest is not the bundled one)
+			let (src_repo, target_repo) = (image_repo(src_uri), image_repo(&target_uris[0]));
+			if let Some(digest) = image["digest"].as_str() {
+				let target_digest = (!realm.is_side_load())
+					.then(|| self.d_repo_digest(&target_uris[0], Some(realm)))
+					.flatten();
+				let target_pinned = match target_digest {
+					Some(target_digest) => format!("{}@{}", target_repo, target_digest),
+					None => target_uris[0].to_string(),
+				};
+				replacements.push((format!("{}@{}", src_repo, digest), target_pinned));
+			}
+			replacements.push((format!("{}:", src_repo), format!("{}:", target_repo)));
+		}
+
+		// -- apply the k8s files, in order
+		let k8s_files: Vec<&str> = bundle["k8s_files"]
+			.as_array()
+			.into_iter()
+			.flatten()
+			.filter_map(|v| v.as_str())
+			.collect();
+		for k8s_file in k8s_files {
+			let path = load_dir.join(k8s_file);
+			let mut content = read_to_string(&path).map_err(|ex| KddError::BundleInvalid(bundle_name.to_string(), ex.to_string()))?;
+			for (from, to) in replacements.iter() {
+				content = content.replace(from, to);
+			}
+			write(&path, content)?;
+
+			let mut args = vec!["apply".to_string(), "-f".to_string(), path_to_string(&path)?];
+			if let Some(ctx) = realm.context() {
+				args.extend(["--context".to_string(), ctx.to_string()]);
+			}
+			exec_cmd_args(Some(&self.dir), "kubectl", &args)?;
+			println!();
+		}
+
+		println!("Bundle {} loaded into realm {} ({} images)", bundle_name, realm.name, images.len());
+		Ok(())
+	}
+
+	fn realm_by_name(&self, name: &str) -> Result<&Realm, KddError> {
+		self.realms.get(name).ok_or_else(|| KddError::RealmNotFound(name.to_string()))
+	}
+}
+
+// region:    Utils
+/// The absolute path of this path (relative to the current dir), as tar runs with `-C`
+fn abs_path(path: &Path) -> Result<PathBuf, KddError> {
+	if path.is_absolute() {
+		Ok(path.to_path_buf())
+	} else {
+		Ok(current_dir()?.join(path))
+	}
+}
+// endregion: Utils
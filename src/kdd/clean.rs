@@ -0,0 +1,89 @@
+////////////////////////////////////
+// kdd::clean - Clean what kdd and the builders produce
+////
+
+use super::{builder::RunOccurrence, error::KddError, proc_log::ProcLog, Kdd};
+use crate::utils::{exec_cmd_args, runner::is_dry_run};
+use std::collections::HashSet;
+use std::fs::{read_dir, remove_dir_all, remove_file};
+
+impl Kdd {
+	/// Run the builders `clean` execs of the blocks and, when no blocks are selected, remove the realms rendered k8s files
+	/// (they are not per block, and `dprune` reads them to protect the deployed tags).
+	/// With `images`, also remove the local docker images (and their realm tags) of the blocks.
+	/// In dry-run, the clean execs and image removals are printed by the runner, and the files to remove are listed.
+	#[tokio::main(flavor = "current_thread")]
+	pub async fn clean(&self, names: Option<&[&str]>, images: bool) -> Result<(), KddError> {
+		let (blocks, _) = self.blocks_for_names(names, false)?;
+
+		// -- run the builders clean execs
+		let mut builders_cleaned: HashSet<&str> = HashSet::new();
+		for block in blocks.iter() {
+			let block_dir = self.get_block_dir(block);
+			for builder in self.builders_for_block(block)? {
+				if let Some(clean) = &builder.clean {
+					if builder.run == RunOccurrence::Session && builders_cleaned.contains(builder.name.as_str()) {
+						continue;
+					}
+					builders_cleaned.insert(&builder.name);
+
+					let log = ProcLog::new(self, &block.name, &format!("{}-clean", builder.name))?;
+					if let Err(ex) = clean.execute_and_wait(&self.dir, &block_dir, false, &log, None).await {
+						println!(
+							"KDD WARNING - clean of builder '{}' for [{}] failed. Cause: {}",
+							builder.name, block.name, ex
+						);
+					}
+				}
+			}
+		}
+
+		// -- remove the rendered k8s files of all realms (only for a full clean)
+		if names.is_none() {
+			let prefix = if is_dry_run() { "(dry-run) would " } else { "" };
+			for realm in self.realms() {
+				let out_dir = realm.k8s_out_dir();
+				if let Ok(entries) = read_dir(&out_dir) {
+					for entry in entries.flatten() {
+						let path = entry.path();
+						println!("{}remove: {}", prefix, path.to_string_lossy());
+						if !is_dry_run() {
+							if path.is_dir() {
+								remove_dir_all(&path)?;
+							} else {
+								remove_file(&path)?;
+							}
+						}
+					}
+				}
+			}
+		}
+
+		// -- remove the local docker images (local registry uri, and the eventual realm uris)
+		if images {
+			let (docker_blocks, _) = self.blocks_for_names(names, true)?;
+			let current_realm = self.current_realm().ok().flatten();
+			let engine = self.engine(current_realm)?;
+			for block in docker_blocks {
+				let mut image_uris = vec![self.image_uri(block, None)?];
+				for realm in self.realms() {
+					let uri = self.image_uri(block, Some(realm))?;
+					if !image_uris.contains(&uri) {
+						image_uris.push(uri);
+					}
+				}
+				for image_uri in image_uris {
+					// only the images present locally
+					if self.d_image_id(&image_uri, current_realm).is_some() {
+						println!("remove image: {}", image_uri);
+						if let Err(ex) = exec_cmd_args(None, engine.cmd(), &engine.rmi_args(&image_uri)) {
+							println!("KDD WARNING - fail to remove image {}. Cause: {}", image_uri, ex);
+						}
+					}
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
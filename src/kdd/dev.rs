@@ -0,0 +1,157 @@
+////////////////////////////////////
+// kdd::dev - The dev inner loop (`kdd dev`): on change, build > dbuild > dpush > kapply, with the block logs tailed
+////
+
+use super::{error::KddError, realm::Realm, report::BuildReport, watcher::BlockWatcher, Block, Kdd};
+use crate::utils::{exec_cmd_args, supervisor::subscribe_stop};
+use std::fs::read_to_string;
+use std::future::pending;
+
+impl Kdd {
+	/// Build, docker build, push, and re-apply the realm k8s files referencing the blocks, and then do it again on
+	/// block changes, while tailing the blocks logs.
+	/// Stops on Ctrl-C (forwarded to the running steps), or right away on a second Ctrl-C.
+	#[tokio::main]
+	pub async fn dev(&self, realm: &Realm, names: Option<&[&str]>) -> Result<(), KddError> {
+		let (blocks, _) = self.blocks_for_names(names, false)?;
+		let mut watcher = BlockWatcher::new(self, &blocks)?;
+
+		// -- Ctrl-C, first to stop (forwarded to the children), second to force exit (see supervisor)
+		let mut stop = subscribe_stop();
+
+		// -- first cycle (all blocks)
+		tokio::select! {
+			_ = self.dev_cycle_or_warn(realm, &blocks) => (),
+			_ = stop.wait() => {
+				println!("KDD - dev stopped");
+				return Ok(());
+			}
+		}
+
+		// -- the logs of the docker blocks (a klog failure does not stop the dev loop)
+		let (docker_blocks, _) = self.blocks_for_names(names, true)?;
+		let log_names: Vec<String> = docker_blocks.iter().map(|b| b.name.to_string()).collect();
+		let logs = async {
+			if !log_names.is_empty() {
+				if let Err(ex) = self.k_log_follow(Some(log_names)).await {
+					println!("KDD WARNING - klog stopped. Cause: {}", ex);
+				}
+			}
+			pending::<()>().await
+		};
+
+		// -- the dev loop
+		let dev_loop = async {
+			println!("====== Dev - watching [{}]...\n", block_names(&blocks).join(", "));
+			while let Some((changed, affected)) = watcher.next_change().await {
+				println!(
+					"==================   Dev - change in [{}] - updating [{}] ==================",
+					changed.join(", "),
+					block_names(&affected).join(", ")
+				);
+				self.dev_cycle_or_warn(realm, &affected).await;
+				println!("==================  /Dev - change in [{}] DONE - watching...\n", changed.join(", "));
+
+				// drop the changes made by the builders themselves (e.g., generated files)
+				watcher.skip_pending().await;
+			}
+		};
+
+		tokio::select! {
+			_ = dev_loop => (),
+			_ = logs => (),
+			_ = stop.wait() => (),
+		}
+		println!("KDD - dev stopped");
+
+		Ok(())
+	}
+
+	async fn dev_cycle_or_warn(&self, realm: &Realm, blocks: &[&Block]) {
+		if let Err(ex) = self.dev_cycle(realm, blocks).await {
+			println!("KDD WARNING - dev update failed (will retry on next change). Cause: {}", ex);
+		}
+	}
+
+	/// One dev cycle for these blocks (build, docker build, push, kapply, and restart of the block workloads)
+	async fn dev_cycle(&self, realm: &Realm, blocks: &[&Block]) -> Result<(), KddError> {
+		let names = block_names(blocks);
+
+		// -- the docker blocks (built with their dependencies), and the other blocks (only built)
+		let (docker_blocks, _) = self.blocks_for_names(Some(&names), true)?;
+		let docker_names = block_names(&docker_blocks);
+		let other_names: Vec<&str> = blocks
+			.iter()
+			.filter(|b| !docker_names.contains(&b.name.as_str()))
+			.filter(|b| {
+				!docker_blocks
+					.iter()
+					.any(|d| d.dependencies.iter().flatten().any(|dep| dep == &b.name))
+			})
+			.map(|b| b.name.as_str())
+			.collect();
+
+		// -- build and docker build (which pushes to the local registry, as `kdd dbuild`)
+		let mut report = BuildReport::new("dev", Some(&realm.name));
+		let mut res = Ok(());
+		if !other_names.is_empty() {
+			res = self.build_blocks(Some(&other_names), false, false, &mut report).await;
+		}
+		if res.is_ok() && !docker_names.is_empty() {
+			res = self.build_blocks(Some(&docker_names), true, false, &mut report).await;
+			// for a remote registry realm, push explicitly
+			if res.is_ok() && !realm.is_local_registry() {
+				res = self.d_push(realm, Some(&docker_names), 1, &mut report);
+			}
+		}
+		report.print_summary();
+		res?;
+		if !report.is_success() {
+			println!("KDD WARNING - some builders failed, skipping kapply");
+			return Ok(());
+		}
+
+		// -- re-apply the k8s files referencing the blocks
+		let k8s_names = self.dev_k8s_names(realm, blocks);
+		if k8s_names.is_empty() {
+			return Ok(());
+		}
+		let k8s_names: Vec<&str> = k8s_names.iter().map(|n| n.as_str()).collect();
+		self.k_apply(realm, Some(&k8s_names))?;
+
+		// -- restart the workloads of the docker blocks (the image uri does not change, so the apply might not)
+		for block in docker_blocks {
+			let label = format!("run={}", self.image_name(block));
+			let args = &["rollout", "restart", "deployment,statefulset,daemonset", "-l", &label];
+			if let Err(ex) = exec_cmd_args(Some(&self.dir), "kubectl", args) {
+				println!("KDD WARNING - fail to restart the workloads of [{}]. Cause: {}", block.name, ex);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Returns the names of the realm k8s files (default configurations if any) referencing these blocks,
+	/// i.e., named as the block, or containing the block image name (e.g., `cstar-web-server`)
+	fn dev_k8s_names(&self, realm: &Realm, blocks: &[&Block]) -> Vec<String> {
+		let config_names = realm.default_configurations();
+		realm
+			.k8s_files(config_names.as_deref())
+			.iter()
+			.filter_map(|file| {
+				let stem = file.file_stem()?.to_str()?;
+				let content = read_to_string(file).ok()?;
+				blocks
+					.iter()
+					.any(|b| b.name == stem || content.contains(&self.image_name(b)))
+					.then(|| stem.to_string())
+			})
+			.collect()
+	}
+}
+
+// region:    Utils
+fn block_names<'a>(blocks: &[&'a Block]) -> Vec<&'a str> {
+	blocks.iter().map(|b| b.name.as_str()).collect()
+}
+// endregion: Utils
@@ -2,89 +2,488 @@
 // kdd::docker - handle all docker actions
 ////
 
-use super::{error::KddError, realm::Realm, Block, Kdd};
-use crate::utils::exec_cmd_args;
+use super::{
+	docker_settings::DockerSettings,
+	error::KddError,
+	image_state::image_repo,
+	proc_log::ProcLog,
+	realm::Realm,
+	registry::{registry_manifest_digest, LOCAL_REGISTRY},
+	report::{BuildReport, StepReport},
+	run_policy::policy_error,
+	Block, Kdd,
+};
+use crate::utils::{path_to_string, query_to_stdout};
+use handlebars::Handlebars;
+use pathdiff::diff_paths;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+/// Where the Dockerfile templates get rendered (relative to the `.kdd/` dir)
+const DOCKER_RENDER_DIR: &str = "docker";
 
 impl Kdd {
 	// e.g., docker build --rm -t localhost:5000/cstar-db:DROP-002-SNAPSHOT .
-	pub fn d_build_block(&self, block: &Block) -> Result<(), KddError> {
+	// (with the block `docker:` settings, e.g., -f Dockerfile.prod --target runtime --build-arg NODE_ENV=production ../..)
+	// or, with `buildx: true`, docker buildx build -t localhost:5000/cstar-db:DROP-002-SNAPSHOT --load ...
+	// (with the realm container engine, e.g., podman build -t ...)
+	pub fn d_build_block(&self, block: &Block, realm: Option<&Realm>) -> Result<(), KddError> {
 		let cwd = self.get_block_dir(&block);
 
-		let image_uri = &self.image_uri(block, None);
+		let image_uri = &self.image_uri(block, None)?;
+		let mut settings = self.d_build_settings(block, realm)?;
+		let engine = self.engine(realm)?;
 
 		// exec command
-		let args = &["build", "--rm", "-t", &image_uri, "."];
+		let args: Vec<String> = if settings.is_buildx() {
+			if !engine.has_buildx() {
+				return Err(KddError::ContainerEngineNoBuildx(block.name.to_string(), engine.cmd().to_string()));
+			}
+			let remote_realm = realm.filter(|r| !r.is_local_registry());
+			let platforms = settings.platforms();
+			let mut args: Vec<String> = vec![
+				"buildx".to_string(),
+				"build".to_string(),
+				"-t".to_string(),
+				image_uri.to_string(),
+			];
+			match (remote_realm, platforms.len() > 1) {
+				// multi platforms images cannot be loaded locally, so, just build (and warm the cache) as dpush will push it by buildx
+				(Some(_), true) => println!(
+					"KDD INFO - multi platforms build for '{}' (not loaded locally, pushed by buildx on dpush)",
+					block.name
+				),
+				(None, true) => {
+					println!(
+						"KDD WARNING - multi platforms build for '{}' cannot be loaded locally, building for {} only",
+						block.name, platforms[0]
+					);
+					settings.platforms = Some(vec![platforms[0].to_string()]);
+					args.push("--load".to_string());
+				}
+				(_, false) => args.push("--load".to_string()),
+			}
+			if let Some(realm) = remote_realm {
+				args.extend(settings.cache_args(&self.image_cache_ref(block, realm)?, false));
+			}
+			args.extend(settings.build_args(&self.docker_build_vars(block, realm))?);
+			args
+		} else {
+			let mut args = engine.build_args(image_uri);
+			args.extend(settings.build_args(&self.docker_build_vars(block, realm))?);
+			args
+		};
 
-		match exec_cmd_args(Some(&cwd), "docker", args) {
+		let log = ProcLog::new(self, &block.name, "docker")?;
+		let policy = &self.docker_policy;
+		let label = format!("{} build", engine.cmd());
+		match policy.run(&label, &log, || log.exec_cmd_args(Some(&cwd), engine.cmd(), &args, policy.timeout)) {
 			Ok(_) => Ok(()),
-			Err(ex) => Err(KddError::FailDockerBuilder(ex.to_string())),
+			Err(ex) => Err(policy_error(ex, KddError::FailDockerBuilder)),
 		}
 	}
 
-	pub fn d_push(&self, realm: &Realm, names: Option<&[&str]>) -> Result<(), KddError> {
+	/// Push the blocks images (`jobs` at a time), skipping the ones already in the realm registry with the same digest
+	pub fn d_push(&self, realm: &Realm, names: Option<&[&str]>, jobs: usize, report: &mut BuildReport) -> Result<(), KddError> {
 		let (blocks, _) = self.blocks_for_names(names, true)?;
 
 		realm.provider().before_dpushes(&self.system, realm, &blocks)?;
 
-		for block in blocks {
-			self.d_push_block(realm, block)?;
+		// -- push the blocks (each worker takes the next block, and no new block after a failure)
+		let next_idx = AtomicUsize::new(0);
+		let failed = AtomicBool::new(false);
+		// (block index, step, push result)
+		type PushResult = (usize, StepReport, Result<Option<String>, KddError>);
+		let results: Mutex<Vec<PushResult>> = Mutex::new(Vec::new());
+		let image_uris = blocks
+			.iter()
+			.map(|block| self.image_uri(block, Some(realm)))
+			.collect::<Result<Vec<_>, _>>()?;
+		thread::scope(|scope| {
+			for _ in 0..jobs.max(1).min(blocks.len()) {
+				scope.spawn(|| loop {
+					let idx = next_idx.fetch_add(1, Ordering::SeqCst);
+					let Some(block) = blocks.get(idx) else { break };
+					if failed.load(Ordering::SeqCst) {
+						break;
+					}
+
+					let image_uri = &image_uris[idx];
+					let (step, res) = match self.d_pushed_digest(realm, block) {
+						Some(digest) => {
+							println!("======  Skip pushing {} (same digest in the registry)", image_uri);
+							let mut step = StepReport::skipped(&image_uri, "same digest in the registry");
+							step.digest = Some(digest.to_string());
+							(step, Ok(Some(digest)))
+						}
+						None => {
+							let start = Instant::now();
+							let res = self.d_push_block(realm, block);
+							let mut step = StepReport::from_result(&image_uri, start, &res);
+							step.digest = res.as_ref().ok().cloned().flatten();
+							(step, res)
+						}
+					};
+					if res.is_err() {
+						failed.store(true, Ordering::SeqCst);
+					}
+					results.lock().unwrap().push((idx, step, res));
+				});
+			}
+		});
+
+		// -- report and record the pushed digests (for `{{images_pinned.<block>}}`), in the block order
+		let mut results = results.into_inner().unwrap();
+		results.sort_by_key(|(idx, _, _)| *idx);
+		let mut first_err = None;
+		for (idx, step, res) in results {
+			let block = blocks[idx];
+			report.block(&block.name).push = Some(step);
+			match res {
+				// not for the side-loaded (local image id)
+				Ok(Some(digest)) if !realm.is_side_load() => {
+					self.save_image_digest(realm, block, &image_uris[idx], &digest)?;
+				}
+				Err(ex) if first_err.is_none() => first_err = Some(ex),
+				_ => (),
+			}
 		}
 
-		Ok(())
+		match first_err {
+			Some(ex) => Err(ex),
+			None => Ok(()),
+		}
+	}
+
+	/// Returns the digest of this block image if the local build is the one already in the registry with all of
+	/// its realm tags (i.e., nothing to push). Only for the plain pushes (not side-loaded, nor buildx).
+	pub fn d_pushed_digest(&self, realm: &Realm, block: &Block) -> Option<String> {
+		if realm.is_side_load() || (self.docker_settings(block, Some(realm)).is_buildx() && !realm.is_local_registry()) {
+			return None;
+		}
+		let image_uris = self.image_uris(block, Some(realm)).ok()?;
+		let local_id = self.d_image_id(&self.image_uri(block, None).ok()?, Some(realm));
+		let realm_id = self.d_image_id(&image_uris[0], Some(realm));
+		// the realm tag repo digest (only if it was pushed to this repo)
+		let repo_digest = self.d_repo_digest(&image_uris[0], Some(realm));
+		pushed_digest(local_id.as_deref(), realm_id.as_deref(), repo_digest, &image_uris, |uri| {
+			self.d_remote_digest(uri, realm)
+		})
+	}
+
+	/// Returns the registry manifest digest of this image uri
+	/// (from the registry api for a local registry, otherwise with the engine `manifest inspect`)
+	pub fn d_remote_digest(&self, image_uri: &str, realm: &Realm) -> Option<String> {
+		if realm.is_local_registry() {
+			return registry_manifest_digest(image_uri);
+		}
+		let engine = self.engine(Some(realm)).ok()?;
+		let json = query_to_stdout(None, engine.cmd(), &engine.manifest_inspect_args(image_uri)?).ok()?;
+		// Note: an array for a manifest list (multi platforms), not compared
+		let json = serde_json::from_str::<Value>(&json).ok()?;
+		json["Descriptor"]["digest"].as_str().map(|digest| digest.to_string())
 	}
 
-	pub fn d_push_block(&self, realm: &Realm, block: &Block) -> Result<(), KddError> {
+	/// Push the block image to the realm registry (with all of the realm image tags),
+	/// and returns its repo digest (if found).
+	/// With `buildx: true` and a remote registry realm, the (multi platforms) image is built and pushed by buildx.
+	pub fn d_push_block(&self, realm: &Realm, block: &Block) -> Result<Option<String>, KddError> {
 		let cwd = &self.dir;
 
-		let local_image_uri = &self.image_uri(block, None);
-		let remote_image_uri = &self.image_uri(block, Some(realm));
+		let local_image_uri = &self.image_uri(block, None)?;
+		let remote_image_uris = self.image_uris(block, Some(realm))?;
+		// the primary one (for the display and digest)
+		let remote_image_uri = &remote_image_uris[0];
 
-		println!("======  Pushing image {} : {}", local_image_uri, remote_image_uri);
-		// make sure the tags exist
-		exec_cmd_args(Some(cwd), "docker", &["tag", local_image_uri, remote_image_uri])?;
+		// local clusters (kind, k3d, minikube), no registry, load the images into the cluster
+		if realm.is_side_load() {
+			return self.d_side_load_block(realm, block);
+		}
+
+		let settings = self.docker_settings(block, Some(realm));
+		let engine = self.engine(Some(realm))?;
+		let buildx = settings.is_buildx() && !realm.is_local_registry();
+		if buildx && !engine.has_buildx() {
+			return Err(KddError::ContainerEngineNoBuildx(block.name.to_string(), engine.cmd().to_string()));
+		}
+
+		println!("======  Pushing image {} : {}", local_image_uri, remote_image_uris.join(", "));
+		let log = ProcLog::new(self, &block.name, "dpush")?;
+
+		// -- the buildx build --push args (with all of the tags), or make sure the tags exist
+		let mut buildx_args: Vec<String> = Vec::new();
+		if buildx {
+			let settings = self.d_build_settings(block, Some(realm))?;
+			buildx_args.extend(["buildx".to_string(), "build".to_string(), "--push".to_string()]);
+			for remote_image_uri in remote_image_uris.iter() {
+				buildx_args.extend(["-t".to_string(), remote_image_uri.to_string()]);
+			}
+			buildx_args.extend(settings.cache_args(&self.image_cache_ref(block, realm)?, true));
+			buildx_args.extend(settings.build_args(&self.docker_build_vars(block, Some(realm)))?);
+		} else {
+			for remote_image_uri in remote_image_uris.iter() {
+				log.exec_cmd_args(Some(cwd), engine.cmd(), &engine.tag_args(local_image_uri, remote_image_uri), None)?;
+			}
+		}
+		let block_dir = self.get_block_dir(block);
 
-		match (
-			realm.is_local_registry(),
-			exec_cmd_args(Some(cwd), "docker", &["push", remote_image_uri]),
-		) {
-			// push successful, just forward Ok(())
+		let policy = &self.docker_policy;
+		let label = format!("{} push", engine.cmd());
+		let local = realm.is_local_registry();
+		let push = || -> Result<(), KddError> {
+			if buildx {
+				policy.run("docker buildx push", &log, || {
+					log.exec_cmd_args(Some(&block_dir), engine.cmd(), &buildx_args, policy.timeout)
+				})?;
+				return Ok(());
+			}
+			for remote_image_uri in remote_image_uris.iter() {
+				policy.run(&label, &log, || {
+					log.exec_cmd_args(Some(cwd), engine.cmd(), &engine.push_args(remote_image_uri, local), policy.timeout)
+				})?;
+			}
+			Ok(())
+		};
+		let digest = |uri: &str| {
+			if buildx {
+				self.d_manifest_digest(uri)
+			} else {
+				self.d_repo_digest(uri, Some(realm))
+			}
+		};
+
+		match (local, push()) {
+			// push successful, just forward the digest
 			(_, Ok(_)) => {
 				println!("====== /Pushing image {} : {} - DONE\n", local_image_uri, remote_image_uri);
-				Ok(())
+				Ok(digest(remote_image_uri))
 			}
 			// if local registry realm and error, canot be recovered, forward error
 			(true, Err(ex)) => {
-				println!("Failed to do a docker push (cause: {})", ex);
-				Err(KddError::DpushFailed(ex.to_string()))
+				println!("Failed to do a {} (cause: {})", label, ex);
+				Err(policy_error(ex, KddError::DpushFailed))
 			}
 			// if remote realm, then, try to recover one time
 			(false, Err(ex)) => {
-				println!("Failed to do a docker push (cause: {})", ex);
+				println!("Failed to do a {} (cause: {})", label, ex);
 				println!("Trying to recover...");
 				// authenticate, and ignore error for now (will fail later)
-				let _ = realm.provider().docker_auth(realm);
+				let _ = realm.provider().docker_auth(realm, engine);
 				// try again
-				match exec_cmd_args(Some(cwd), "docker", &["push", remote_image_uri]) {
+				match push() {
 					Ok(_) => {
 						println!("Recovered OK!");
 						println!("====== /Pushing image {} : {} - DONE\n", local_image_uri, remote_image_uri);
-						Ok(())
+						Ok(digest(remote_image_uri))
 					}
 					Err(ex) => {
 						println!("Failed recover (cause: {})", ex);
 						println!("====== /Pushing image {} : {} - FAILED\n", local_image_uri, remote_image_uri);
-						Err(KddError::DpushFailed(ex.to_string()))
+						Err(policy_error(ex, KddError::DpushFailed))
 					}
 				}
 			}
 		}
 	}
 
-	fn image_uri(&self, block: &Block, realm: Option<&Realm>) -> String {
-		let registry = realm.map(|r| r.registry.as_deref()).flatten().unwrap_or("localhost:5000");
-		let registry = registry.trim_end_matches('/');
-		let image_name = self.image_name(block);
+	/// Tag and load the block image into the realm local cluster (e.g., `kind load docker-image`),
+	/// and returns the image id
+	fn d_side_load_block(&self, realm: &Realm, block: &Block) -> Result<Option<String>, KddError> {
+		let cwd = &self.dir;
+		let engine = self.engine(Some(realm))?;
+		let local_image_uri = &self.image_uri(block, None)?;
+		let image_uris = self.image_uris(block, Some(realm))?;
+
+		println!(
+			"======  Loading image {} : {} (realm {})",
+			local_image_uri,
+			image_uris.join(", "),
+			realm.name
+		);
+		let log = ProcLog::new(self, &block.name, "dpush")?;
+		let policy = &self.docker_policy;
+		for image_uri in image_uris.iter() {
+			log.exec_cmd_args(Some(cwd), engine.cmd(), &engine.tag_args(local_image_uri, image_uri), None)?;
+			let load = realm.provider().side_load_image(realm, image_uri)?;
+			policy
+				.run(&load.cmd, &log, || log.exec_cmd_args(Some(cwd), &load.cmd, &load.args, policy.timeout))
+				.map_err(|ex| policy_error(ex, KddError::DpushFailed))?;
+		}
+		println!("====== /Loading image {} : {} - DONE\n", local_image_uri, image_uris[0]);
 
-		format!("{}/{}:{}", registry, &image_name, self.image_tag())
+		Ok(self.d_image_id(&image_uris[0], Some(realm)))
+	}
+
+	/// Returns the docker settings of this block for the build, with the `file` as the rendered Dockerfile
+	/// when it is a template (e.g., `Dockerfile.hbs`)
+	fn d_build_settings(&self, block: &Block, realm: Option<&Realm>) -> Result<DockerSettings, KddError> {
+		let mut settings = self.docker_settings(block, realm);
+		let docker_file = self.docker_file(block);
+		if settings.is_template(&docker_file) {
+			let rendered = self.d_render_dockerfile(block, realm, &docker_file)?;
+			settings.file = Some(path_to_string(&rendered)?);
+		}
+		Ok(settings)
+	}
+
+	/// Render this Dockerfile template with the template vars (as the k8s files) into `.kdd/docker/<block>.Dockerfile`
+	fn d_render_dockerfile(&self, block: &Block, realm: Option<&Realm>, docker_file: &Path) -> Result<PathBuf, KddError> {
+		let src_name = diff_paths(docker_file, &self.dir).unwrap_or_else(|| docker_file.to_path_buf());
+		let src_name = src_name.to_string_lossy().to_string();
+
+		let mut vars = self.template_vars(realm)?;
+		vars.insert("image_name".to_string(), Value::String(self.image_name(block)));
+
+		// Note: the errors as `<file>:<line>:<col>` (the template name is the file)
+		let mut hbs = Handlebars::new();
+		hbs.register_template_string(&src_name, read_to_string(docker_file)?)
+			.map_err(|ex| KddError::DockerfileFailRender(err_location(&src_name, ex.line_no, ex.column_no), ex.reason.to_string()))?;
+		let content = hbs
+			.render(&src_name, &Value::Object(vars))
+			.map_err(|ex| KddError::DockerfileFailRender(err_location(&src_name, ex.line_no, ex.column_no), ex.desc))?;
+
+		// Note: absolute, as the docker build runs in the block dir
+		let out_dir = self.local_dir().join(DOCKER_RENDER_DIR);
+		create_dir_all(&out_dir)?;
+		let out_file = out_dir.canonicalize()?.join(format!("{}.Dockerfile", block.name));
+		write(&out_file, content)?;
+		Ok(out_file)
+	}
+
+	/// Returns the local image id (e.g., `sha256:...`) of this image uri (None if not found)
+	pub fn d_image_id(&self, image_uri: &str, realm: Option<&Realm>) -> Option<String> {
+		let engine = self.engine(realm).ok()?;
+		let id = query_to_stdout(None, engine.cmd(), &engine.image_id_args(image_uri)).ok()?;
+		let id = id.trim();
+		id.starts_with("sha256:").then(|| id.to_string())
+	}
+
+	/// Returns the repo digest (e.g., `sha256:...`) of this pushed image uri (None if not found)
+	pub fn d_repo_digest(&self, image_uri: &str, realm: Option<&Realm>) -> Option<String> {
+		let repo = image_repo(image_uri);
+		let engine = self.engine(realm).ok()?;
+		let digests = query_to_stdout(None, engine.cmd(), &engine.repo_digests_args(image_uri)).ok()?;
+		// Note: `<repo>@sha256:...` lines, or just the `sha256:...` (e.g., buildah)
+		digests
+			.lines()
+			.map(|line| line.trim())
+			.find_map(|line| match line.strip_prefix(repo).and_then(|d| d.strip_prefix('@')) {
+				Some(digest) => Some(digest),
+				None => line.starts_with("sha256:").then_some(line),
+			})
+			.map(|digest| digest.to_string())
+	}
+
+	/// Returns the registry manifest digest of this uri (e.g., for a buildx pushed multi platforms image)
+	pub fn d_manifest_digest(&self, image_uri: &str) -> Option<String> {
+		let args = ["buildx", "imagetools", "inspect", "--format", "{{.Manifest.Digest}}", image_uri];
+		let digest = query_to_stdout(None, "docker", &args).ok()?;
+		let digest = digest.trim();
+		digest.starts_with("sha256:").then(|| digest.to_string())
+	}
+
+	pub fn image_uri(&self, block: &Block, realm: Option<&Realm>) -> Result<String, KddError> {
+		Ok(realm_image_uri(&self.image_name(block), &self.image_tag(realm)?, realm))
+	}
+
+	/// Returns the image uris for all of the image tags of this realm (the primary first)
+	pub fn image_uris(&self, block: &Block, realm: Option<&Realm>) -> Result<Vec<String>, KddError> {
+		let image_uri = self.image_uri(block, realm)?;
+		let repo = image_uri.rsplit_once(':').map(|(repo, _)| repo).unwrap_or(&image_uri);
+		Ok(self.image_tags(realm)?.iter().map(|tag| format!("{}:{}", repo, tag)).collect())
+	}
+
+	/// Returns the buildx registry cache ref of this block for this realm (e.g., `<registry>/<image_name>:buildcache`)
+	pub fn image_cache_ref(&self, block: &Block, realm: &Realm) -> Result<String, KddError> {
+		let image_uri = self.image_uri(block, Some(realm))?;
+		let repo = image_uri.rsplit_once(':').map(|(repo, _)| repo).unwrap_or(&image_uri);
+		Ok(format!("{}:buildcache", repo))
+	}
+
+	/// The vars for the docker build args (as for the tasks and hooks)
+	fn docker_build_vars(&self, block: &Block, realm: Option<&Realm>) -> HashMap<String, String> {
+		let mut vars = self.merged_vars(realm);
+		vars.insert("block".to_string(), block.name.to_string());
+		if let Some(realm) = realm {
+			vars.insert("realm".to_string(), realm.name.to_string());
+		}
+		vars
 	}
 }
+
+// region:    Utils
+/// Returns the repo digest if the local build (`local_id`) is the realm tagged image (`realm_id`),
+/// and all of the realm image uris are in the registry with this digest.
+/// Note: After a dbuild, the realm tags are still on the previously pushed image (same digest as the registry),
+///       so, the ids must match as well.
+pub fn pushed_digest<F>(
+	local_id: Option<&str>,
+	realm_id: Option<&str>,
+	repo_digest: Option<String>,
+	image_uris: &[String],
+	remote_digest: F,
+) -> Option<String>
+where
+	F: Fn(&str) -> Option<String>,
+{
+	match (local_id, realm_id) {
+		(Some(local_id), Some(realm_id)) if local_id == realm_id => (),
+		_ => return None,
+	}
+	let digest = repo_digest?;
+	for image_uri in image_uris.iter() {
+		if remote_digest(image_uri)? != digest {
+			return None;
+		}
+	}
+	Some(digest)
+}
+
+fn err_location(file: &str, line: Option<usize>, col: Option<usize>) -> String {
+	match (line, col) {
+		(Some(line), Some(col)) => format!("{}:{}:{}", file, line, col),
+		(Some(line), None) => format!("{}:{}", file, line),
+		_ => file.to_string(),
+	}
+}
+
+/// The image uri of this image name and tag for this realm (e.g., `<registry>/cstar-agent:1.0.0`)
+pub fn realm_image_uri(image_name: &str, image_tag: &str, realm: Option<&Realm>) -> String {
+	// side-loaded into the local cluster (no registry), e.g., `cstar-agent:1.0.0`
+	if realm.map(|r| r.is_side_load()).unwrap_or(false) {
+		return format!("{}:{}", image_name, image_tag);
+	}
+
+	let registry = realm.and_then(|r| r.registry.as_deref()).unwrap_or(LOCAL_REGISTRY);
+	let registry = registry.trim_end_matches('/');
+
+	format!("{}/{}:{}", registry, image_name, image_tag)
+}
+
+/// Returns true if this is a docker tag (`[A-Za-z0-9_][A-Za-z0-9_.-]{0,127}`) which is not dangling,
+/// i.e., not ending with a separator (e.g., `dev-` for a `dev-{{git_sha}}` without the sha)
+pub fn is_valid_image_tag(tag: &str) -> bool {
+	let valid_char = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-';
+	match (tag.chars().next(), tag.chars().last()) {
+		(Some(first), Some(last)) => {
+			(first.is_ascii_alphanumeric() || first == '_')
+				&& last.is_ascii_alphanumeric()
+				&& tag.len() <= 128
+				&& tag.chars().all(valid_char)
+		}
+		_ => false,
+	}
+}
+// endregion: Utils
+
+// region:    Tests
+#[cfg(test)]
+#[path = "../_test/kdd_docker.rs"]
+mod tests;
+// endregion: Tests
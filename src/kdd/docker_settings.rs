@@ -0,0 +1,214 @@
+////////////////////////////////////
+// kdd::docker_settings - The docker build settings (kdd.yaml, realm, and block `docker:` sections)
+////
+
+use super::{error::KddError, realm::Realm, Block, Kdd};
+use crate::utils::yamls::{as_string, as_strings, to_string};
+use handlebars::Handlebars;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use yaml_rust::Yaml;
+
+const DOCKER_KEY_FILE: &str = "file";
+const DOCKER_KEY_CONTEXT: &str = "context";
+const DOCKER_KEY_ARGS: &str = "args";
+const DOCKER_KEY_TARGET: &str = "target";
+const DOCKER_KEY_PLATFORM: &str = "platform";
+const DOCKER_KEY_FLAGS: &str = "flags";
+const DOCKER_KEY_BUILDX: &str = "buildx";
+const DOCKER_KEY_PLATFORMS: &str = "platforms";
+const DOCKER_KEY_CACHE: &str = "cache";
+const DOCKER_KEY_TEMPLATE: &str = "template";
+
+const DOCKER_FILE_DEFAULT: &str = "Dockerfile";
+const DOCKER_FILE_TEMPLATE: &str = "Dockerfile.hbs";
+const DOCKER_CONTEXT_DEFAULT: &str = ".";
+
+/// The docker build settings. The block ones override the realm ones, which override the kdd.yaml ones
+/// (args are merged by name, and flags are appended).
+#[derive(Debug, Clone, Default)]
+pub struct DockerSettings {
+	/// The Dockerfile path, relative to the block dir (default `Dockerfile`)
+	pub file: Option<String>,
+	/// The build context dir, relative to the block dir (default `.`, e.g., `../..` for a monorepo root)
+	pub context: Option<String>,
+	/// The `--build-arg` values, rendered (handlebars) at build time with the vars, realm, and block
+	pub args: Vec<(String, String)>,
+	pub target: Option<String>,
+	pub platform: Option<String>,
+	/// Extra `docker build` flags (e.g., `--pull`, `--no-cache`)
+	pub flags: Vec<String>,
+	/// Opt-in `docker buildx build` (multi platforms, registry cache, and push by buildx for the remote registry realms)
+	pub buildx: Option<bool>,
+	/// The buildx platforms (e.g., `[linux/amd64, linux/arm64]`), take precedence over `platform`
+	pub platforms: Option<Vec<String>>,
+	/// For buildx, use the realm registry `<image>:buildcache` as `--cache-from`/`--cache-to` (remote registry realms only)
+	pub cache: Option<bool>,
+	/// The Dockerfile is a handlebars template (implicit for a `.hbs` file, e.g., `Dockerfile.hbs`)
+	pub template: Option<bool>,
+}
+
+//// DockerSettings Builder(s)
+impl DockerSettings {
+	pub fn from_yaml(yaml: &Yaml) -> DockerSettings {
+		let args = match yaml[DOCKER_KEY_ARGS].as_hash() {
+			Some(map) => map
+				.iter()
+				.filter_map(|(name, val)| Some((name.as_str()?.to_string(), to_string(val)?)))
+				.collect(),
+			None => Vec::new(),
+		};
+
+		DockerSettings {
+			file: as_string(yaml, DOCKER_KEY_FILE),
+			context: as_string(yaml, DOCKER_KEY_CONTEXT),
+			args,
+			target: as_string(yaml, DOCKER_KEY_TARGET),
+			platform: as_string(yaml, DOCKER_KEY_PLATFORM),
+			flags: as_strings(yaml, DOCKER_KEY_FLAGS).unwrap_or_default(),
+			buildx: yaml[DOCKER_KEY_BUILDX].as_bool(),
+			platforms: as_strings(yaml, DOCKER_KEY_PLATFORMS),
+			cache: yaml[DOCKER_KEY_CACHE].as_bool(),
+			template: yaml[DOCKER_KEY_TEMPLATE].as_bool(),
+		}
+	}
+
+	/// The realm `docker:` settings. The `file` and `context` are about the block layout, so, ignored for a realm
+	pub fn from_realm_yaml(realm_name: &str, yaml: &Yaml) -> DockerSettings {
+		let mut settings = DockerSettings::from_yaml(yaml);
+		let (file, context) = (settings.file.take(), settings.context.take());
+		if file.is_some() || context.is_some() {
+			eprintln!(
+				"KDD PARSING WARNING - realm {} docker file/context ignored (only for kdd.yaml and blocks)",
+				realm_name
+			);
+		}
+		settings
+	}
+}
+
+//// DockerSettings Public Methods
+impl DockerSettings {
+	/// Returns these settings overridden by the other ones
+	pub fn merge(&self, other: &DockerSettings) -> DockerSettings {
+		let mut args = self.args.clone();
+		for (name, val) in other.args.iter() {
+			match args.iter_mut().find(|(n, _)| n == name) {
+				Some(arg) => arg.1 = val.to_string(),
+				None => args.push((name.to_string(), val.to_string())),
+			}
+		}
+
+		DockerSettings {
+			file: other.file.clone().or_else(|| self.file.clone()),
+			context: other.context.clone().or_else(|| self.context.clone()),
+			args,
+			target: other.target.clone().or_else(|| self.target.clone()),
+			platform: other.platform.clone().or_else(|| self.platform.clone()),
+			flags: self.flags.iter().chain(other.flags.iter()).cloned().collect(),
+			buildx: other.buildx.or(self.buildx),
+			platforms: other.platforms.clone().or_else(|| self.platforms.clone()),
+			cache: other.cache.or(self.cache),
+			template: other.template.or(self.template),
+		}
+	}
+
+	pub fn is_buildx(&self) -> bool {
+		self.buildx.unwrap_or(false)
+	}
+
+	pub fn is_cache(&self) -> bool {
+		self.cache.unwrap_or(false)
+	}
+
+	/// True if this Dockerfile is to be rendered (the `template:` setting when set, otherwise, for a `.hbs` file)
+	pub fn is_template(&self, docker_file: &Path) -> bool {
+		self.template
+			.unwrap_or_else(|| docker_file.extension().map(|ext| ext == "hbs").unwrap_or(false))
+	}
+
+	/// Returns the target platforms (the buildx `platforms`, otherwise the `platform`)
+	pub fn platforms(&self) -> Vec<String> {
+		match (&self.platforms, &self.platform) {
+			(Some(platforms), _) if !platforms.is_empty() => platforms.clone(),
+			(_, Some(platform)) => vec![platform.to_string()],
+			_ => Vec::new(),
+		}
+	}
+
+	/// Returns the `docker build` args (without the `build` and `-t <image>`), with the build args rendered.
+	/// e.g., `-f Dockerfile.prod --target runtime --platform linux/arm64 --build-arg NODE_ENV=production ../..`
+	pub fn build_args(&self, vars: &HashMap<String, String>) -> Result<Vec<String>, KddError> {
+		let mut args: Vec<String> = Vec::new();
+		if let Some(file) = &self.file {
+			args.extend(["-f".to_string(), file.to_string()]);
+		}
+		if let Some(target) = &self.target {
+			args.extend(["--target".to_string(), target.to_string()]);
+		}
+		let platforms = self.platforms();
+		if !platforms.is_empty() {
+			args.extend(["--platform".to_string(), platforms.join(",")]);
+		}
+
+		let hbs = Handlebars::new();
+		for (name, val) in self.args.iter() {
+			let val = hbs
+				.render_template(val, vars)
+				.map_err(|ex| KddError::DockerArgFailRender(name.to_string(), ex.to_string()))?;
+			args.extend(["--build-arg".to_string(), format!("{}={}", name, val)]);
+		}
+
+		args.extend(self.flags.iter().cloned());
+		args.push(self.context.as_deref().unwrap_or(DOCKER_CONTEXT_DEFAULT).to_string());
+
+		Ok(args)
+	}
+
+	/// Returns the buildx `--cache-from` (and `--cache-to` when `write`) args for this cache image ref
+	/// (empty if no `cache: true`)
+	pub fn cache_args(&self, cache_ref: &str, write: bool) -> Vec<String> {
+		if !self.is_cache() {
+			return Vec::new();
+		}
+		let mut args = vec!["--cache-from".to_string(), format!("type=registry,ref={}", cache_ref)];
+		if write {
+			args.extend(["--cache-to".to_string(), format!("type=registry,ref={},mode=max", cache_ref)]);
+		}
+		args
+	}
+}
+
+//// Kdd Docker Settings Methods
+impl Kdd {
+	/// Returns the docker settings of this block for this realm (kdd.yaml < realm < block)
+	pub fn docker_settings(&self, block: &Block, realm: Option<&Realm>) -> DockerSettings {
+		let settings = match realm {
+			Some(realm) => self.docker_settings.merge(&realm.docker),
+			None => self.docker_settings.clone(),
+		};
+		settings.merge(&block.docker)
+	}
+
+	/// Returns the Dockerfile path of this block (as per the kdd.yaml and block `docker.file`),
+	/// by default the `Dockerfile.hbs` template if present, otherwise the `Dockerfile`
+	pub fn docker_file(&self, block: &Block) -> PathBuf {
+		let block_dir = self.get_block_dir(block);
+		match block.docker.file.as_deref().or(self.docker_settings.file.as_deref()) {
+			Some(file) => block_dir.join(file),
+			None => {
+				let template = block_dir.join(DOCKER_FILE_TEMPLATE);
+				if template.is_file() {
+					template
+				} else {
+					block_dir.join(DOCKER_FILE_DEFAULT)
+				}
+			}
+		}
+	}
+
+	/// Returns true if this block has a Dockerfile (i.e., it is a docker block)
+	pub fn has_dockerfile(&self, block: &Block) -> bool {
+		self.docker_file(block).is_file()
+	}
+}
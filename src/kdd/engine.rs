@@ -0,0 +1,267 @@
+////////////////////////////////////
+// kdd::engine - The container engines (docker, podman, nerdctl, buildah) and their command differences
+////
+
+use super::{error::KddError, realm::Realm, Kdd};
+use std::env;
+
+/// The env var overriding the kdd.yaml and realm `container_engine:` (e.g., `KDD_CONTAINER_ENGINE=podman`)
+pub const ENV_CONTAINER_ENGINE: &str = "KDD_CONTAINER_ENGINE";
+
+/// The container engine commands args. The default implementation is the docker one.
+pub trait ContainerEngine {
+	/// The engine cli (e.g., `docker`, `podman`)
+	fn cmd(&self) -> &'static str;
+
+	/// Only docker has buildx
+	fn has_buildx(&self) -> bool {
+		false
+	}
+
+	/// If it can run containers (e.g., the local registry)
+	fn can_run(&self) -> bool {
+		true
+	}
+
+	/// e.g., `build --rm -t <image_uri>` (followed by the docker settings build args)
+	fn build_args(&self, image_uri: &str) -> Vec<String> {
+		to_strings(&["build", "--rm", "-t", image_uri])
+	}
+
+	fn tag_args(&self, image_uri: &str, target_uri: &str) -> Vec<String> {
+		to_strings(&["tag", image_uri, target_uri])
+	}
+
+	/// `local` for the local registry (e.g., `localhost:5000`, plain http)
+	fn push_args(&self, image_uri: &str, _local: bool) -> Vec<String> {
+		to_strings(&["push", image_uri])
+	}
+
+	/// The login args, with the password as stdin
+	fn login_args(&self, username: &str, registry: &str) -> Vec<String> {
+		to_strings(&["login", "--username", username, "--password-stdin", registry])
+	}
+
+	/// The args printing the image id (e.g., `sha256:...`)
+	fn image_id_args(&self, image_uri: &str) -> Vec<String> {
+		to_strings(&["image", "inspect", "-f", "{{.Id}}", image_uri])
+	}
+
+	/// The args printing the image repo digests, one per line (e.g., `<repo>@sha256:...`)
+	fn repo_digests_args(&self, image_uri: &str) -> Vec<String> {
+		to_strings(&[
+			"image",
+			"inspect",
+			"-f",
+			"{{range .RepoDigests}}{{println .}}{{end}}",
+			image_uri,
+		])
+	}
+
+	/// The args printing the remote manifest (json) of this image uri, None if not supported by the engine
+	fn manifest_inspect_args(&self, image_uri: &str) -> Option<Vec<String>> {
+		Some(to_strings(&["manifest", "inspect", "-v", image_uri]))
+	}
+
+	/// The args listing the local tags of this repository, as `<tag>\t<created at>\t<image id>` lines
+	fn list_tags_args(&self, repo: &str) -> Vec<String> {
+		to_strings(&["images", "--format", "{{.Tag}}\t{{.CreatedAt}}\t{{.ID}}", repo])
+	}
+
+	fn rmi_args(&self, image_uri: &str) -> Vec<String> {
+		to_strings(&["rmi", image_uri])
+	}
+
+	/// The args saving these images into one archive file (for `kdd bundle`), None if not supported by the engine
+	fn save_args(&self, file: &str, image_uris: &[String]) -> Option<Vec<String>> {
+		let mut args = to_strings(&["save", "-o", file]);
+		args.extend(image_uris.iter().cloned());
+		Some(args)
+	}
+
+	/// The args loading the images of an archive file (for `kdd bundle load`)
+	fn load_args(&self, file: &str) -> Option<Vec<String>> {
+		Some(to_strings(&["load", "-i", file]))
+	}
+}
+
+pub struct DockerEngine;
+
+pub struct PodmanEngine;
+
+pub struct NerdctlEngine;
+
+pub struct BuildahEngine;
+
+/// The `container_engine:` value (kdd.yaml, realm, or KDD_CONTAINER_ENGINE)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineKind {
+	Docker,
+	Podman,
+	Nerdctl,
+	Buildah,
+}
+
+//// EngineKind Builder(s)
+impl EngineKind {
+	pub fn from_name(name: &str) -> Option<EngineKind> {
+		match name.trim().to_lowercase().as_str() {
+			"docker" => Some(EngineKind::Docker),
+			"podman" => Some(EngineKind::Podman),
+			"nerdctl" => Some(EngineKind::Nerdctl),
+			"buildah" => Some(EngineKind::Buildah),
+			_ => None,
+		}
+	}
+
+	/// Parse a kdd.yaml or realm `container_engine:` value (print a warning if unknown)
+	pub fn from_setting(name: Option<String>) -> Option<EngineKind> {
+		let name = name?;
+		let kind = EngineKind::from_name(&name);
+		if kind.is_none() {
+			eprintln!(
+				"KDD PARSING WARNING - container_engine '{}' unknown (docker, podman, nerdctl, buildah), ignored",
+				name
+			);
+		}
+		kind
+	}
+}
+
+//// EngineKind Public Methods
+impl EngineKind {
+	pub fn engine(&self) -> &'static dyn ContainerEngine {
+		match self {
+			EngineKind::Docker => &DockerEngine,
+			EngineKind::Podman => &PodmanEngine,
+			EngineKind::Nerdctl => &NerdctlEngine,
+			EngineKind::Buildah => &BuildahEngine,
+		}
+	}
+}
+
+//// Kdd Engine Methods
+impl Kdd {
+	/// Returns the container engine for this realm (KDD_CONTAINER_ENGINE, then realm, then kdd.yaml, then docker)
+	pub fn engine(&self, realm: Option<&Realm>) -> Result<&'static dyn ContainerEngine, KddError> {
+		let kind = match env::var(ENV_CONTAINER_ENGINE) {
+			Ok(name) if !name.trim().is_empty() => EngineKind::from_name(&name).ok_or_else(|| KddError::ContainerEngineUnknown(name.to_string()))?,
+			_ => realm
+				.and_then(|r| r.container_engine)
+				.or(self.container_engine)
+				.unwrap_or(EngineKind::Docker),
+		};
+		Ok(kind.engine())
+	}
+}
+
+// region:    Docker Engine
+impl ContainerEngine for DockerEngine {
+	fn cmd(&self) -> &'static str {
+		"docker"
+	}
+
+	fn has_buildx(&self) -> bool {
+		true
+	}
+}
+// endregion: Docker Engine
+
+// region:    Podman Engine
+impl ContainerEngine for PodmanEngine {
+	fn cmd(&self) -> &'static str {
+		"podman"
+	}
+
+	fn manifest_inspect_args(&self, _image_uri: &str) -> Option<Vec<String>> {
+		None
+	}
+
+	fn push_args(&self, image_uri: &str, local: bool) -> Vec<String> {
+		insecure_push_args(image_uri, local, "--tls-verify=false")
+	}
+
+	// Note: podman saves only one image per archive without `--multi-image-archive`
+	fn save_args(&self, file: &str, image_uris: &[String]) -> Option<Vec<String>> {
+		let mut args = to_strings(&["save", "--multi-image-archive", "-o", file]);
+		args.extend(image_uris.iter().cloned());
+		Some(args)
+	}
+}
+// endregion: Podman Engine
+
+// region:    Nerdctl Engine
+impl ContainerEngine for NerdctlEngine {
+	fn cmd(&self) -> &'static str {
+		"nerdctl"
+	}
+
+	fn manifest_inspect_args(&self, _image_uri: &str) -> Option<Vec<String>> {
+		None
+	}
+
+	// Note: nerdctl build (buildkit) does not have `--rm`
+	fn build_args(&self, image_uri: &str) -> Vec<String> {
+		to_strings(&["build", "-t", image_uri])
+	}
+
+	fn push_args(&self, image_uri: &str, local: bool) -> Vec<String> {
+		insecure_push_args(image_uri, local, "--insecure-registry")
+	}
+}
+// endregion: Nerdctl Engine
+
+// region:    Buildah Engine
+impl ContainerEngine for BuildahEngine {
+	fn cmd(&self) -> &'static str {
+		"buildah"
+	}
+
+	fn manifest_inspect_args(&self, _image_uri: &str) -> Option<Vec<String>> {
+		None
+	}
+
+	fn can_run(&self) -> bool {
+		false
+	}
+
+	fn build_args(&self, image_uri: &str) -> Vec<String> {
+		to_strings(&["build", "-t", image_uri])
+	}
+
+	fn push_args(&self, image_uri: &str, local: bool) -> Vec<String> {
+		insecure_push_args(image_uri, local, "--tls-verify=false")
+	}
+
+	// Note: buildah image id is without the `sha256:` prefix
+	fn image_id_args(&self, image_uri: &str) -> Vec<String> {
+		to_strings(&["inspect", "--type", "image", "--format", "sha256:{{.FromImageID}}", image_uri])
+	}
+
+	fn repo_digests_args(&self, image_uri: &str) -> Vec<String> {
+		to_strings(&["inspect", "--type", "image", "--format", "{{.FromImageDigest}}", image_uri])
+	}
+
+	fn save_args(&self, _file: &str, _image_uris: &[String]) -> Option<Vec<String>> {
+		None
+	}
+
+	fn load_args(&self, _file: &str) -> Option<Vec<String>> {
+		None
+	}
+}
+// endregion: Buildah Engine
+
+// region:    Utils
+fn to_strings(args: &[&str]) -> Vec<String> {
+	args.iter().map(|a| a.to_string()).collect()
+}
+
+fn insecure_push_args(image_uri: &str, local: bool, insecure_flag: &str) -> Vec<String> {
+	if local {
+		to_strings(&["push", insecure_flag, image_uri])
+	} else {
+		to_strings(&["push", image_uri])
+	}
+}
+// endregion: Utils
@@ -14,6 +14,9 @@ pub enum KddError {
 	#[error("Invalid builder '{0}'. {1}")]
 	InvalidBuilder(String, String),
 
+	#[error("Builders of block '{0}' have a cycle in their after/before constraints (between: {1})")]
+	BuildersCycle(String, String),
+
 	#[error("Invalid builder exec '{0}'. {1} ")]
 	InvalidBuilderExec(String, String),
 
@@ -35,6 +38,9 @@ pub enum KddError {
 	#[error("Block {0} unknown. Build aborted")]
 	BlockUnknown(String),
 
+	#[error("No {0} matching '{1}'{2}")]
+	SelectorNoMatch(String, String, String),
+
 	#[error("Cannot dpush, no current realm")]
 	DpushFailNoRealm,
 
@@ -56,6 +62,51 @@ pub enum KddError {
 	#[error("No exec.cmd found")]
 	NoExecCmd,
 
+	#[error("Fail to render exec '{0}' cause: {1}")]
+	ExecFailRender(String, String),
+
+	#[error("Fail to render Dockerfile template '{0}' cause: {1}")]
+	DockerfileFailRender(String, String),
+
+	#[error("Fail to render docker build arg '{0}' cause: {1}")]
+	DockerArgFailRender(String, String),
+
+	#[error("Unknown container engine '{0}' (docker, podman, nerdctl, buildah)")]
+	ContainerEngineUnknown(String),
+
+	#[error("Block '{0}' has buildx, which is only supported by the docker container engine (not {1})")]
+	ContainerEngineNoBuildx(String, String),
+
+	#[error("The {0} container engine cannot run the local registry container (docker, podman, or nerdctl)")]
+	ContainerEngineCannotRun(String),
+
+	#[error("The {0} container engine cannot save or load image archives (docker, podman, or nerdctl)")]
+	ContainerEngineNoArchive(String),
+
+	#[error("Image '{0}' not found locally (dbuild it first)")]
+	BundleImageNotFound(String),
+
+	#[error("Invalid bundle '{0}'. Cause: {1}")]
+	BundleInvalid(String, String),
+
+	#[error("Registry request '{0}' failed. Cause: {1}")]
+	RegistryRequestFailed(String, String),
+
+	#[error("Task '{0}' unknown")]
+	TaskUnknown(String),
+
+	#[error("Tasks have a cycle in their dependencies (between: {0})")]
+	TasksCycle(String),
+
+	#[error("Hook '{0}' failed. Cause: {1}")]
+	HookFailed(String, String),
+
+	#[error("Invalid watch '{0}' value '{1}'")]
+	InvalidWatchConfig(String, String),
+
+	#[error("Fail to watch files. Cause: {0}")]
+	WatchFailed(String),
+
 	#[error("Fail exec proc, Cause: {0}")]
 	FailExecProc(String),
 
@@ -65,6 +116,12 @@ pub enum KddError {
 	#[error("Fail to execute, cause: {0}")]
 	KubectlFail(String),
 
+	#[error("Realm '{0}' provider '{1}' unknown (aws, gcp, kind, k3d, minikube, common)")]
+	RealmProviderUnknown(String, String),
+
+	#[error("The realm provider does not side-load images")]
+	ProviderNoSideLoad,
+
 	#[error("Realm {0} not found")]
 	RealmNotFound(String),
 
@@ -91,4 +148,22 @@ pub enum KddError {
 
 	#[error("Cannot execute builder - cause: {0} ")]
 	CannotExecute(String),
+
+	#[error("'{0}' failed ({1})")]
+	ExecFailed(String, String),
+
+	#[error("'{0}' timed out after {1}s (process tree killed)")]
+	ExecTimeout(String, u64),
+
+	#[error("'{0}' failed after {1} attempts. Last cause: {2}")]
+	RetriesExhausted(String, u32, String),
+
+	#[error("Invalid '{0}' value '{1}'")]
+	InvalidRunPolicy(String, String),
+
+	#[error("Invalid --older-than '{0}' (e.g., 30d, 12h)")]
+	InvalidPruneOlderThan(String),
+
+	#[error("Invalid image tag '{0}' (empty or dangling, e.g., a {{{{git_sha}}}} that could not be resolved)")]
+	InvalidImageTag(String),
 }
@@ -0,0 +1,170 @@
+////////////////////////////////////
+// kdd::hook - The user lifecycle hooks (kdd.yaml and realm `hooks:`) around the kdd actions
+////
+
+use super::{builder::Exec, error::KddError, proc_log::ProcLog, realm::Realm, Kdd};
+use crate::utils::path_to_string;
+use handlebars::Handlebars;
+use pathdiff::diff_paths;
+use std::{collections::HashMap, path::PathBuf};
+use strum_macros::Display;
+use yaml_rust::Yaml;
+
+// the log "block" name for the hooks
+const HOOK_LOG_BLOCK: &str = "hooks";
+
+/// The actions that can be hooked (e.g., `pre_dbuild`, `post_kapply`)
+#[derive(Debug, Clone, Copy, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum HookAction {
+	Build,
+	DBuild,
+	DPush,
+	KTemplate,
+	KApply,
+	KDelete,
+}
+
+#[derive(Debug, Clone, Copy, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum HookPhase {
+	Pre,
+	Post,
+}
+
+/// The execs per hook name (e.g., `pre_dbuild`), in their declaration order
+#[derive(Debug, Default)]
+pub struct Hooks {
+	execs_by_name: HashMap<String, Vec<Exec>>,
+}
+
+/// What the hooked action is about (passed to the hook execs as vars and env)
+#[derive(Debug, Default)]
+pub struct HookTarget<'a> {
+	pub realm: Option<&'a Realm>,
+	pub blocks: Vec<String>,
+	pub files: Vec<PathBuf>,
+}
+
+//// Hooks Builder(s)
+impl Hooks {
+	/// Parse the `hooks:` yaml map. Each hook can be one exec or a list of execs, e.g.,
+	/// `post_kapply: { cmd: ./scripts/smoke-test.sh }`
+	pub fn from_yaml(yaml: &Yaml) -> Hooks {
+		let mut execs_by_name: HashMap<String, Vec<Exec>> = HashMap::new();
+
+		if let Some(map) = yaml.as_hash() {
+			for (name, y_execs) in map.iter() {
+				let name = match name.as_str() {
+					Some(name) => name,
+					None => continue,
+				};
+				let y_execs = match y_execs {
+					Yaml::Array(y_execs) => y_execs.iter().collect::<Vec<_>>(),
+					y_exec => vec![y_exec],
+				};
+				for y_exec in y_execs {
+					match Exec::from_yaml(y_exec) {
+						Ok(exec) => execs_by_name.entry(name.to_string()).or_default().push(exec),
+						Err(ex) => eprintln!("KDD PARSING WARNING - hook {} does not have a valid exec. Cause: {}. Skipping", name, ex),
+					}
+				}
+			}
+		}
+
+		Hooks { execs_by_name }
+	}
+}
+
+impl Hooks {
+	fn execs(&self, hook_name: &str) -> &[Exec] {
+		self.execs_by_name.get(hook_name).map(|v| v.as_slice()).unwrap_or(&[])
+	}
+}
+
+//// Kdd Hook Methods
+impl Kdd {
+	/// Run the `pre_` hooks, the action, and then (if the action succeeded) the `post_` hooks.
+	/// A failing pre hook aborts the action.
+	///
+	/// Note: This is the sync entry point (one runtime for both phases, not entered while the action runs,
+	///       as the action might start its own). Async callers use `with_hooks_async` instead.
+	pub fn with_hooks<T>(&self, action: HookAction, target: &HookTarget, f: impl FnOnce() -> Result<T, KddError>) -> Result<T, KddError> {
+		let rt = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+		rt.block_on(self.run_hooks(HookPhase::Pre, action, target))?;
+		let res = f()?;
+		rt.block_on(self.run_hooks(HookPhase::Post, action, target))?;
+		Ok(res)
+	}
+
+	/// As `with_hooks`, for the async callers (already on a runtime), e.g., the dpush to the local registry after a docker build
+	pub async fn with_hooks_async<T>(
+		&self,
+		action: HookAction,
+		target: &HookTarget<'_>,
+		f: impl FnOnce() -> Result<T, KddError>,
+	) -> Result<T, KddError> {
+		self.run_hooks(HookPhase::Pre, action, target).await?;
+		let res = f()?;
+		self.run_hooks(HookPhase::Post, action, target).await?;
+		Ok(res)
+	}
+
+	/// Run the kdd.yaml hooks and then the realm hooks for this phase and action
+	pub async fn run_hooks(&self, phase: HookPhase, action: HookAction, target: &HookTarget<'_>) -> Result<(), KddError> {
+		let hook_name = format!("{}_{}", phase, action);
+
+		let mut execs: Vec<&Exec> = self.hooks.execs(&hook_name).iter().collect();
+		if let Some(realm) = target.realm {
+			execs.extend(realm.hooks.execs(&hook_name).iter());
+		}
+		if execs.is_empty() {
+			return Ok(());
+		}
+
+		// -- the vars (for the exec rendering) and env
+		let blocks = target.blocks.join(",");
+		let files = target
+			.files
+			.iter()
+			.map(|f| diff_paths(f, &self.dir).unwrap_or_else(|| f.clone()))
+			.map(|f| path_to_string(&f))
+			.collect::<Result<Vec<String>, _>>()?
+			.join(" ");
+
+		let mut vars = self.merged_vars(target.realm);
+		vars.insert("hook".to_string(), hook_name.to_string());
+		vars.insert("blocks".to_string(), blocks.to_string());
+		vars.insert("files".to_string(), files.to_string());
+		let mut env: Vec<(String, String)> = vec![
+			("KDD_SYSTEM".to_string(), self.system.to_string()),
+			("KDD_DIR".to_string(), path_to_string(&self.dir)?),
+			("KDD_HOOK".to_string(), hook_name.to_string()),
+			("KDD_BLOCKS".to_string(), blocks),
+			("KDD_FILES".to_string(), files),
+		];
+		if let Some(realm) = target.realm {
+			vars.insert("realm".to_string(), realm.name.to_string());
+			env.push(("KDD_REALM".to_string(), realm.name.to_string()));
+		}
+
+		let hbs = Handlebars::new();
+		let count = execs.len();
+		for (idx, exec) in execs.into_iter().enumerate() {
+			let step_name = if count > 1 {
+				format!("{}-{}", hook_name, idx + 1)
+			} else {
+				hook_name.to_string()
+			};
+			println!("======  Hook '{}' ", step_name);
+			let log = ProcLog::new(self, HOOK_LOG_BLOCK, &step_name)?;
+			let exec = exec.render(&hbs, &vars)?.with_env(&env);
+			exec.execute_and_wait(&self.dir, &self.dir, false, &log, None)
+				.await
+				.map_err(|ex| KddError::HookFailed(step_name.to_string(), ex.to_string()))?;
+			println!("====== /Hook '{}' DONE\n", step_name);
+		}
+
+		Ok(())
+	}
+}
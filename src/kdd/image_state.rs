@@ -0,0 +1,102 @@
+////////////////////////////////////
+// kdd::image_state - The block images template vars, with the pushed digests (.kdd/state/<realm>/images.json)
+////
+
+use super::{error::KddError, realm::Realm, Block, Kdd};
+use crate::utils::runner::is_dry_run;
+use serde_json::{json, Map, Value};
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::PathBuf;
+
+const STATE_DIR: &str = "state";
+const IMAGES_FILE: &str = "images.json";
+
+//// Kdd Image State Methods
+impl Kdd {
+	/// Returns the `.kdd/state/<realm>/images.json` path
+	pub fn images_state_path(&self, realm: &Realm) -> PathBuf {
+		self.local_dir().join(STATE_DIR).join(&realm.name).join(IMAGES_FILE)
+	}
+
+	/// Returns the pushed images of this realm, by block name (e.g., `{"agent": {"uri": ..., "digest": ...}}`)
+	pub fn load_images_state(&self, realm: &Realm) -> Map<String, Value> {
+		let path = self.images_state_path(realm);
+		if !path.is_file() {
+			return Map::new();
+		}
+		match read_to_string(&path).map(|content| serde_json::from_str::<Value>(&content)) {
+			Ok(Ok(Value::Object(map))) => map,
+			_ => {
+				println!("KDD WARNING - invalid {}, ignored", path.to_string_lossy());
+				Map::new()
+			}
+		}
+	}
+
+	/// Record the pushed image uri and digest of this block (for the next k8s templates). Nothing is recorded in dry-run.
+	pub fn save_image_digest(&self, realm: &Realm, block: &Block, image_uri: &str, digest: &str) -> Result<(), KddError> {
+		if is_dry_run() {
+			return Ok(());
+		}
+		let path = self.images_state_path(realm);
+		if let Some(dir) = path.parent() {
+			create_dir_all(dir)?;
+		}
+
+		let mut images = self.load_images_state(realm);
+		images.insert(
+			block.name.to_string(),
+			json!({
+				"uri": image_uri,
+				"digest": digest,
+			}),
+		);
+		write(&path, serde_json::to_string_pretty(&Value::Object(images))?)?;
+		Ok(())
+	}
+
+	/// Returns the block image template vars for this realm, by block name:
+	/// - `images` the image uris (e.g., `{{images.web-server}}` for `<registry>/cstar-web-server:<tag>`)
+	/// - `image_names` (e.g., `cstar-web-server`)
+	/// - `image_digests` the pushed digests (empty if not pushed, or pushed with another tag)
+	/// - `images_pinned` the `<repo>@sha256:...` uris (the uri if no digest)
+	pub fn image_vars(&self, realm: &Realm) -> Result<Map<String, Value>, KddError> {
+		let state = self.load_images_state(realm);
+		let (blocks, _) = self.blocks_for_names(None, true)?;
+
+		let (mut images, mut image_names, mut image_digests, mut images_pinned) = (Map::new(), Map::new(), Map::new(), Map::new());
+		for block in blocks {
+			let uri = self.image_uri(block, Some(realm))?;
+			let digest = state
+				.get(&block.name)
+				.filter(|pushed| pushed["uri"].as_str() == Some(uri.as_str()))
+				.and_then(|pushed| pushed["digest"].as_str());
+			let uri_pinned = match digest {
+				Some(digest) => format!("{}@{}", image_repo(&uri), digest),
+				None => uri.to_string(),
+			};
+			let name = block.name.to_string();
+			image_names.insert(name.clone(), json!(self.image_name(block)));
+			image_digests.insert(name.clone(), json!(digest.unwrap_or_default()));
+			images_pinned.insert(name.clone(), json!(uri_pinned));
+			images.insert(name, json!(uri));
+		}
+
+		let mut vars = Map::new();
+		vars.insert("images".to_string(), Value::Object(images));
+		vars.insert("image_names".to_string(), Value::Object(image_names));
+		vars.insert("image_digests".to_string(), Value::Object(image_digests));
+		vars.insert("images_pinned".to_string(), Value::Object(images_pinned));
+		Ok(vars)
+	}
+}
+
+// region:    Utils
+/// The image uri without the tag (the last ':' after the last '/')
+pub fn image_repo(image_uri: &str) -> &str {
+	match image_uri.rsplit_once(':') {
+		Some((repo, tag)) if !tag.contains('/') => repo,
+		_ => image_uri,
+	}
+}
+// endregion: Utils
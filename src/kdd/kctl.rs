@@ -5,7 +5,7 @@
 use std::io::stdin;
 
 use super::{error::KddError, Kdd, Realm};
-use crate::utils::{exec_cmd_args, exec_to_stdout, path_to_string};
+use crate::utils::{exec_cmd_args, exec_to_stdout, path_to_string, query_to_stdout};
 
 impl Kdd {
 	pub fn k_apply(&self, realm: &Realm, names: Option<&[&str]>) -> Result<(), KddError> {
@@ -61,7 +61,7 @@ impl Kdd {
 	}
 
 	pub fn k_list_context(&self) -> Result<Vec<String>, KddError> {
-		match exec_to_stdout(Some(&self.dir), "kubectl", &["config", "get-contexts", "-o=name"], false) {
+		match query_to_stdout(Some(&self.dir), "kubectl", &["config", "get-contexts", "-o=name"]) {
 			Ok(ctxs) => Ok(ctxs.lines().map(|s| s.trim().to_string()).collect()),
 			Err(e) => Err(KddError::KubectlFail(e.to_string())),
 		}
@@ -75,7 +75,7 @@ impl Kdd {
 	}
 
 	pub fn k_current_context(&self) -> Result<String, KddError> {
-		match exec_to_stdout(Some(&self.dir), "kubectl", &["config", "current-context"], false) {
+		match query_to_stdout(Some(&self.dir), "kubectl", &["config", "current-context"]) {
 			Ok(name) => Ok(name.trim().to_string()),
 			Err(ex) => Err(KddError::KubectlFail(ex.to_string())),
 		}
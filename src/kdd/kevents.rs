@@ -1,6 +1,6 @@
 use super::PodsProvider;
 use super::{error::KddError, Kdd, Pod, Realm};
-use crate::utils::jsons;
+use crate::utils::{jsons, runner::ExtCmd, set_process_group, supervisor::ChildGuard};
 use serde_json::Value;
 use std::collections::{HashMap, HashSet};
 use std::format as f;
@@ -66,17 +66,22 @@ pub struct KubeEventData {
 
 pub async fn monitor_kube_events(events_tx: Sender<KubeEvent>) -> Result<(), KddError> {
 	async fn inner(events_tx: Sender<KubeEvent>) -> Result<(), KddError> {
-		let cmd = "kubectl";
-		let args = &["get", "events", "--watch-only", "-o", "json"];
-		let mut proc = Command::new(&cmd);
-		let proc = proc.args(args).stdout(Stdio::piped());
+		let ext_cmd = ExtCmd::new("kubectl", &["get", "events", "--watch-only", "-o", "json"]);
+		if ext_cmd.dry_run() {
+			return Ok(());
+		}
+		let mut proc = ext_cmd.command();
+		proc.stdout(Stdio::piped());
+		set_process_group(&mut proc);
 
-		let mut child = proc.spawn()?;
+		let mut child = Command::from(proc).spawn()?;
+		// kill the kubectl get events when done (or dropped)
+		let _guard = ChildGuard::track(child.id().unwrap_or_default());
 
 		let stdout = child
 			.stdout
 			.take()
-			.ok_or_else(|| KddError::FailExecProc(f!("no stdout to take, for {:?}", cmd)))?;
+			.ok_or_else(|| KddError::FailExecProc(f!("no stdout to take, for {:?}", ext_cmd.cmd)))?;
 		let mut reader = BufReader::new(stdout).lines();
 
 		let mut json_block: Option<String> = None;
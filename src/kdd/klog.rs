@@ -5,6 +5,7 @@
 use super::kevents::{monitor_kube_events, KubeEvent};
 use super::PodsProvider;
 use super::{error::KddError, Kdd, Pod, Realm};
+use crate::utils::{runner::ExtCmd, set_process_group, supervisor::ChildGuard};
 use std::collections::{HashMap, HashSet};
 use std::format as f;
 use std::process::Stdio;
@@ -31,11 +32,20 @@ impl Kdd {
 		show_klogs_for_pods(self, names)?;
 		Ok(())
 	}
+
+	/// The 'kdd klogs' for the async callers (e.g., `kdd dev`). Needs a multi thread runtime.
+	pub async fn k_log_follow(&self, names: Option<Vec<String>>) -> Result<(), KddError> {
+		klogs_for_pods(self, names).await
+	}
 }
 
 // TODO: Should try to reduce the required thread. main_thread/single thread seems to get stuck, but should not need much.
 #[tokio::main]
 async fn show_klogs_for_pods(kdd: &Kdd, service_names: Option<Vec<String>>) -> Result<(), KddError> {
+	klogs_for_pods(kdd, service_names).await
+}
+
+async fn klogs_for_pods(kdd: &Kdd, service_names: Option<Vec<String>>) -> Result<(), KddError> {
 	// TODO: (low) needs to define optimum channel buffer size
 	let (log_tx, mut log_rx) = mpsc::channel::<LogMessage>(32);
 
@@ -161,14 +171,19 @@ async fn refresh_pod_monitors(
 
 /// Will do a kubectl log for a given pod, and send LogMessage to tx
 async fn monitor_pod(pod: Arc<Pod>, tx: Sender<LogMessage>) -> Result<(), KddError> {
-	println!("> kubectl logs -f {}", pod.name);
-	let cmd = "kubectl";
-	let args = &["logs", "-f", &pod.name];
+	let ext_cmd = ExtCmd::new("kubectl", &["logs", "-f", &pod.name]);
+	if ext_cmd.dry_run() {
+		return Ok(());
+	}
+	println!("> {}", ext_cmd.cmd_line());
 
-	let mut proc = Command::new(&cmd);
-	let proc = proc.args(args).stdout(Stdio::piped());
+	let mut proc = ext_cmd.command();
+	proc.stdout(Stdio::piped());
+	set_process_group(&mut proc);
 
-	let mut child = proc.spawn()?;
+	let mut child = Command::from(proc).spawn()?;
+	// kill the kubectl logs when done (or dropped)
+	let _guard = ChildGuard::track(child.id().unwrap_or_default());
 
 	let stdout = child.stdout.take().expect("child did not have a handle to stdout");
 	let mut reader = BufReader::new(stdout).lines();
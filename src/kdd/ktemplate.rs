@@ -3,7 +3,7 @@
 // --
 
 use std::{
-	collections::HashMap,
+	borrow::Cow,
 	fs::{create_dir_all, read_to_string, File},
 	io::Write,
 	path::PathBuf,
@@ -11,6 +11,8 @@ use std::{
 
 use handlebars::{Handlebars, RenderError};
 use pathdiff::diff_paths;
+use regex::{Captures, Regex};
+use serde_json::{Map, Value};
 
 use super::{error::KddError, realm::Realm, Kdd};
 
@@ -24,12 +26,8 @@ impl Kdd {
 			create_dir_all(&out_dir)?;
 		}
 
-		// -- take the kdd vars and merge the realm var on top of it
-		let mut merged_vars = self.vars.clone();
-		for (name, val) in realm.vars.iter() {
-			merged_vars.insert(name.to_string(), val.to_string());
-		}
-		let merged_vars = merged_vars;
+		let merged_vars = self.template_vars(Some(realm))?;
+		let image_names = merged_vars.get("image_names").cloned().unwrap_or_default();
 
 		// -- render the files
 		if print_full {
@@ -42,8 +40,26 @@ impl Kdd {
 				// -- render the content
 				let src_file_rel_path = diff_paths(&src_file, &self.dir).unwrap();
 				let src_content = read_to_string(&src_file)?;
+				let src_content = match upgrade_image_keys(&src_content) {
+					Cow::Borrowed(_) => src_content,
+					Cow::Owned(content) => {
+						println!(
+							"KDD WARNING - {} uses the deprecated {{{{images.<block>.uri|digest|uri_pinned}}}} (now {{{{images.<block>}}}}, {{{{image_digests.<block>}}}}, {{{{images_pinned.<block>}}}})",
+							src_file_rel_path.to_string_lossy()
+						);
+						content
+					}
+				};
 
-				let out_content = match self.k_render_file(&hbs, &src_content, &merged_vars) {
+				// the `image_name` of the block of this file (e.g., `web-server.yaml`)
+				let mut file_vars = merged_vars.clone();
+				let stem = src_file.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+				if let Some(image_name) = image_names.get(stem) {
+					file_vars.insert("image_name".to_string(), image_name.clone());
+				}
+				let file_vars = Value::Object(file_vars);
+
+				let out_content = match self.k_render_file(&hbs, &src_content, &file_vars) {
 					Ok(v) => v,
 					Err(ex) => {
 						return Err(KddError::KtemplateFailRender(
@@ -77,7 +93,45 @@ impl Kdd {
 		Ok(k8s_out_files)
 	}
 
-	fn k_render_file(&self, hbs: &Handlebars<'_>, src_content: &str, vars: &HashMap<String, String>) -> Result<String, RenderError> {
+	/// Returns the template vars (k8s files and Dockerfile templates), the kdd vars with the realm ones on top of them,
+	/// the `image_tag`, and the block images of the realm (e.g., `{{images.web-server}}`, `{{images_pinned.agent}}`)
+	pub fn template_vars(&self, realm: Option<&Realm>) -> Result<Map<String, Value>, KddError> {
+		let mut vars: Map<String, Value> = self
+			.merged_vars(realm)
+			.into_iter()
+			.map(|(name, val)| (name, Value::String(val)))
+			.collect();
+		// the primary image tag of the realm
+		vars.insert("image_tag".to_string(), Value::String(self.image_tag(realm)?));
+		if let Some(realm) = realm {
+			vars.extend(self.image_vars(realm)?);
+		}
+		Ok(vars)
+	}
+
+	fn k_render_file(&self, hbs: &Handlebars<'_>, src_content: &str, vars: &Value) -> Result<String, RenderError> {
 		hbs.render_template(src_content, vars)
 	}
 }
+
+// region:    Utils
+/// Rewrite the previous `{{images.<block>.uri|digest|uri_pinned}}` keys as the
+/// `{{images.<block>}}`, `{{image_digests.<block>}}`, and `{{images_pinned.<block>}}` ones
+pub fn upgrade_image_keys(content: &str) -> Cow<'_, str> {
+	let rgx = Regex::new(r"(\{\{[^}]*?)\bimages\.([\w-]+)\.(uri_pinned|digest|uri)\b").expect("works once, works all the time");
+	rgx.replace_all(content, |caps: &Captures| {
+		let key = match &caps[3] {
+			"uri_pinned" => "images_pinned",
+			"digest" => "image_digests",
+			_ => "images",
+		};
+		format!("{}{}.{}", &caps[1], key, &caps[2])
+	})
+}
+// endregion: Utils
+
+// region:    Tests
+#[cfg(test)]
+#[path = "../_test/kdd_ktemplate.rs"]
+mod tests;
+// endregion: Tests
@@ -3,9 +3,12 @@
 // --
 
 use super::KddConfig;
-use super::{error::KddError, version::Version, Block, Builder, Kdd, Realm};
+use super::{
+	docker_settings::DockerSettings, engine::EngineKind, error::KddError, hook::Hooks, run_policy::RunPolicy, task::Task, version::Version,
+	watcher::WatchConfig, Block, Builder, Kdd, Realm,
+};
 use crate::utils::yamls::{as_string, as_strings, merge_yaml, print_yaml};
-use crate::utils::{has_prop, path_to_string};
+use crate::utils::{has_prop, path_to_string, query_to_stdout};
 use handlebars::Handlebars;
 use indexmap::IndexMap;
 use regex::Regex;
@@ -17,6 +20,17 @@ use yaml_rust::{Yaml, YamlLoader};
 const KDD_KEY_SYSTEM: &str = "system";
 const KDD_KEY_BLOCK_DIR: &str = "block_base_dir";
 const KDD_KEY_IMAGE_TAG: &str = "image_tag";
+const KDD_KEY_IMAGE_TAGS: &str = "image_tags";
+const KDD_KEY_DOCKER: &str = "docker";
+const KDD_KEY_CONTAINER_ENGINE: &str = "container_engine";
+const KDD_KEY_HOOKS: &str = "hooks";
+const KDD_KEY_WATCH: &str = "watch";
+
+// the git short sha var (only set if used in the kdd.yaml)
+const VAR_GIT_SHA: &str = "git_sha";
+
+// the vars only known at run time (tasks, hooks, docker args), which must be escaped (`\{{realm}}`) in the kdd.yaml
+const RUN_TIME_VARS: &[&str] = &["realm", "block", "block_dir", "blocks", "hook", "files"];
 
 // Kdev Builder
 impl Kdd {
@@ -35,12 +49,26 @@ impl Kdd {
 			return Err(KddError::NoKddFileFound(dir.to_string_lossy().to_string()));
 		}
 		let kdd_content = read_to_string(kdd_path)?;
+
 		let KddRawPart {
 			kdd_yaml_txt,
 			vars: extra_vars,
 			overlays,
 		} = parse_kdd_raw_part(&dir, &kdd_content)?;
 
+		// the git short sha (e.g., for the image tags), only when used in the kdd.yaml or its overlays (to not run git otherwise)
+		if kdd_content.contains(VAR_GIT_SHA) || overlays.iter().any(|overlay| overlay.contains(VAR_GIT_SHA)) {
+			match git_short_sha(&dir) {
+				Some(sha) => {
+					root_vars.insert(VAR_GIT_SHA.to_owned(), sha);
+				}
+				None => eprintln!(
+					"KDD WARNING - '{}' is used but cannot be resolved (git missing or not a git repo), it will render empty",
+					VAR_GIT_SHA
+				),
+			}
+		}
+
 		// add to root vars
 		merge_vars(&mut root_vars, extra_vars);
 
@@ -50,6 +78,7 @@ impl Kdd {
 			kdd_yaml,
 			blocks,
 			builders,
+			tasks,
 			versions,
 			system,
 			realm_base,
@@ -81,6 +110,19 @@ impl Kdd {
 			}
 		}
 
+		// -- docker steps timeout and retries
+		let docker_policy = RunPolicy::from_yaml(&kdd_yaml[KDD_KEY_DOCKER])?;
+		// -- docker build settings (same `docker:` section)
+		let docker_settings = DockerSettings::from_yaml(&kdd_yaml[KDD_KEY_DOCKER]);
+		// -- docker, podman, nerdctl, or buildah
+		let container_engine = EngineKind::from_setting(as_string(&kdd_yaml, KDD_KEY_CONTAINER_ENGINE));
+
+		// -- lifecycle hooks (the realm ones are parsed with the realm)
+		let hooks = Hooks::from_yaml(&kdd_yaml[KDD_KEY_HOOKS]);
+
+		// -- kdd watch debounce and ignore globs
+		let watch_config = WatchConfig::from_yaml(&kdd_yaml[KDD_KEY_WATCH])?;
+
 		// -- build final kdd
 		let kdd_config = KddConfig {
 			vars: root_vars,
@@ -89,10 +131,17 @@ impl Kdd {
 			// both has to come from first kdd_yaml
 			block_base_dir: as_string(&kdd_yaml, KDD_KEY_BLOCK_DIR),
 			image_tag: as_string(&kdd_yaml, KDD_KEY_IMAGE_TAG),
+			image_tags: as_strings(&kdd_yaml, KDD_KEY_IMAGE_TAGS).unwrap_or_default(),
 			blocks,
 			realms,
 			builders,
+			tasks,
 			versions,
+			docker_policy,
+			docker_settings,
+			container_engine,
+			hooks,
+			watch_config,
 		};
 
 		Ok(Kdd::from(kdd_config))
@@ -156,6 +205,7 @@ struct KddPart {
 	realms: IndexMap<String, Realm>,
 	realm_base: Option<Yaml>,
 	builders: Vec<Builder>,
+	tasks: Vec<Task>,
 	versions: Vec<Version>,
 	kdd_yaml: Yaml,
 }
@@ -167,7 +217,13 @@ fn parse_kdd_part(
 	hbs: &Handlebars,
 	realm_root_base: &Option<Yaml>,
 ) -> Result<KddPart, KddError> {
-	// handlebars process the kdd yaml text
+	// handlebars process the kdd yaml text (the unescaped run time vars would silently render empty)
+	for name in unescaped_run_time_vars(kdd_yaml_txt, root_vars) {
+		eprintln!(
+			"KDD PARSING WARNING - '{{{{{}}}}}' is rendered empty when loading the kdd.yaml, escape it as '\\{{{{{}}}}}' to render it at run time",
+			name, name
+		);
+	}
 	let rendered_yaml = match hbs.render_template(&kdd_yaml_txt, &root_vars) {
 		Ok(r) => r,
 		Err(e) => return Err(KddError::KdevFailToParseInvalid(e.to_string())),
@@ -188,6 +244,9 @@ fn parse_kdd_part(
 	// -- read the builders
 	let builders = parse_builders(&kdd_yaml["builders"]);
 
+	// -- read the tasks
+	let tasks = parse_tasks(&kdd_yaml["tasks"]);
+
 	// -- read the versions
 	let versions = parser_versions(&kdd_yaml["versions"]);
 
@@ -206,11 +265,37 @@ fn parse_kdd_part(
 		realms,
 		realm_base,
 		builders,
+		tasks,
 		versions,
 		kdd_yaml,
 	})
 }
 
+/// Returns the git short sha of the HEAD (None if not a git repo)
+fn git_short_sha(dir: &PathBuf) -> Option<String> {
+	let sha = query_to_stdout(Some(dir), "git", &["rev-parse", "--short", "HEAD"]).ok()?;
+	let sha = sha.trim();
+	// Note: on failure, the stderr is returned
+	(!sha.is_empty() && sha.chars().all(|c| c.is_ascii_hexdigit())).then(|| sha.to_string())
+}
+
+/// Returns the run time vars (e.g., `{{realm}}`) used unescaped in this kdd yaml text, and not defined at load time
+fn unescaped_run_time_vars(kdd_yaml_txt: &str, vars: &HashMap<String, String>) -> Vec<&'static str> {
+	let rx = Regex::new(r"(\\)?\{\{\{?\s*(\w+)\s*\}").expect("works once, works all the time");
+	let mut names: Vec<&'static str> = Vec::new();
+	for caps in rx.captures_iter(kdd_yaml_txt) {
+		if caps.get(1).is_some() {
+			continue;
+		}
+		if let Some(name) = RUN_TIME_VARS.iter().find(|n| **n == &caps[2]) {
+			if !vars.contains_key(*name) && !names.contains(name) {
+				names.push(name);
+			}
+		}
+	}
+	names
+}
+
 fn merge_vars(root_vars: &mut HashMap<String, String>, vars: HashMap<String, String>) {
 	for (name, val) in vars.into_iter() {
 		root_vars.insert(name, val);
@@ -248,8 +333,8 @@ fn load_vars(dir: &PathBuf, yamls: &Vec<Yaml>) -> HashMap<String, String> {
 				match (has_prop(&yaml_item, "from_file"), has_prop(&yaml_item, "from_env")) {
 					(Some(from_file_yaml), None) => load_vars_from_file(dir, from_file_yaml, &mut vars),
 					(None, Some(from_env_yaml)) => load_vars_from_env(from_env_yaml, &mut vars),
-					(None, None) => println!("KDD WARNING - no valid vars yaml item. Skip."),
-					(Some(_), Some(_)) => println!("KDD WARNING - vars items cannot have from_file and from_env. Skip"),
+					(None, None) => eprintln!("KDD WARNING - no valid vars yaml item. Skip."),
+					(Some(_), Some(_)) => eprintln!("KDD WARNING - vars items cannot have from_file and from_env. Skip"),
 				}
 			}
 		}
@@ -284,11 +369,11 @@ fn load_vars_from_file(dir: &PathBuf, yaml_item: &Yaml, vars: &mut HashMap<Strin
 						}
 					}
 					Err(ex) => {
-						println!("KDD WARNING - Invalid json for {} ex: {} - SKIP", path.to_string_lossy(), ex);
+						eprintln!("KDD WARNING - Invalid json for {} ex: {} - SKIP", path.to_string_lossy(), ex);
 					}
 				},
 				Err(ex) => {
-					println!("KDD WARNING - Cannot read from {} because {} - SKIP", path.to_string_lossy(), ex);
+					eprintln!("KDD WARNING - Cannot read from {} because {} - SKIP", path.to_string_lossy(), ex);
 				}
 			},
 			FileVarsSource::Toml(path) => match read_to_string(&path) {
@@ -313,15 +398,15 @@ fn load_vars_from_file(dir: &PathBuf, yaml_item: &Yaml, vars: &mut HashMap<Strin
 						}
 					}
 					Err(ex) => {
-						println!("KDD WARNING - Invalid toml for {} ex: {} - SKIP", path.to_string_lossy(), ex);
+						eprintln!("KDD WARNING - Invalid toml for {} ex: {} - SKIP", path.to_string_lossy(), ex);
 					}
 				},
 				Err(ex) => {
-					println!("KDD WARNING - Cannot read from {} because {} - SKIP", path.to_string_lossy(), ex);
+					eprintln!("KDD WARNING - Cannot read from {} because {} - SKIP", path.to_string_lossy(), ex);
 				}
 			},
 			FileVarsSource::NotSupported(path) => {
-				println!("KDD WARNING - file {} not supported as a variable source. - SKIP", path.to_string_lossy());
+				eprintln!("KDD WARNING - file {} not supported as a variable source. - SKIP", path.to_string_lossy());
 			}
 		}
 	}
@@ -337,13 +422,13 @@ fn load_overlays(dir: &PathBuf, pre_yamls: &Vec<Yaml>) -> Vec<String> {
 		if let Some(files) = as_strings(pre_yaml, "overlays") {
 			for file in files {
 				if let Ok(content) = read_to_string(dir.join(&file)) {
-					println!("KDD INFO - overlay file {} loaded", file);
+					eprintln!("KDD INFO - overlay file {} loaded", file);
 					overlays.push(content);
 				}
 			}
 			// empty line
 			if overlays.len() > 0 {
-				println!();
+				eprintln!();
 			}
 		}
 	}
@@ -388,7 +473,7 @@ fn parse_realms(kdd_dir: &PathBuf, y_realms: &Yaml, realms_base_vars: &Option<Ya
 						Ok(realm) => {
 							realms.insert(name.to_string(), realm);
 						}
-						Err(ex) => println!("KDD ERROR - Fail to parse realm {}. Cause: {}", name, ex),
+						Err(ex) => eprintln!("KDD ERROR - Fail to parse realm {}. Cause: {}", name, ex),
 					}
 				}
 			}
@@ -423,6 +508,16 @@ fn parse_builders(y_builders: &Yaml) -> Vec<Builder> {
 
 // endregion: Builders Parser
 
+// region:    Tasks Parser
+fn parse_tasks(y_tasks: &Yaml) -> Vec<Task> {
+	let tasks = y_tasks
+		.as_vec()
+		.map(|y_tasks| y_tasks.iter().filter_map(Task::from_yaml).collect::<Vec<Task>>());
+
+	tasks.unwrap_or_default()
+}
+// endregion: Tasks Parser
+
 // region:    Version Parser
 fn parser_versions(y_versions: &Yaml) -> Vec<Version> {
 	let versions = y_versions.as_vec().map(|y_versions| {
@@ -3,19 +3,35 @@
 ////
 
 mod block;
+pub mod blocks;
 mod build;
 mod builder;
+mod bundle;
+mod clean;
+mod dev;
 mod docker;
+mod docker_settings;
+mod engine;
 pub mod error;
+pub mod hook;
+mod image_state;
 mod kctl;
 mod kevents;
 mod kexec;
 mod klog;
 mod ktemplate;
 mod loader;
+mod proc_log;
 mod provider;
+pub mod prune;
 mod realm;
+mod registry;
+pub mod report;
+mod run_policy;
+mod selector;
+mod task;
 pub mod version;
+mod watcher;
 
 use handlebars::Handlebars;
 use std::collections::HashSet;
@@ -24,12 +40,19 @@ use std::{
 	path::{Path, PathBuf},
 };
 
-use crate::utils::exec_to_stdout;
+use crate::utils::query_to_stdout;
 
-use self::{block::Block, builder::Builder, error::KddError, realm::Realm, version::Version};
+use self::docker::is_valid_image_tag;
+use self::{
+	block::Block, builder::Builder, docker_settings::DockerSettings, engine::EngineKind, error::KddError, hook::Hooks, realm::Realm,
+	run_policy::RunPolicy, task::Task, version::Version, watcher::WatchConfig,
+};
 use indexmap::IndexMap;
 use serde_json::Value;
 
+/// Directory (relative to the kdd dir) where kdd writes its own files (e.g., logs)
+const KDD_LOCAL_DIR: &str = ".kdd";
+
 #[derive(Debug)]
 pub struct KddConfig {
 	// hbs: Handlebars,
@@ -39,11 +62,18 @@ pub struct KddConfig {
 	system: String,
 	block_base_dir: Option<String>,
 	image_tag: Option<String>,
+	image_tags: Vec<String>,
 
 	realms: IndexMap<String, Realm>,
 	blocks: Vec<Block>,
 	builders: Vec<Builder>,
+	tasks: Vec<Task>,
 	versions: Vec<Version>,
+	docker_policy: RunPolicy,
+	docker_settings: DockerSettings,
+	container_engine: Option<EngineKind>,
+	hooks: Hooks,
+	watch_config: WatchConfig,
 }
 
 #[derive(Debug)]
@@ -55,11 +85,24 @@ pub struct Kdd {
 	system: String,
 	block_base_dir: Option<String>,
 	image_tag: Option<String>,
+	/// the extra image tags (on top of the image_tag), for the dpush
+	image_tags: Vec<String>,
 
 	realms: IndexMap<String, Realm>,
 	blocks: Vec<Block>,
 	builders: Vec<Builder>,
+	tasks: Vec<Task>,
 	versions: Vec<Version>,
+	/// timeout and retries for the docker build and push steps (from the kdd.yaml `docker:` section)
+	docker_policy: RunPolicy,
+	/// the kdd.yaml `docker:` build settings (file, context, args, ...), overridden by the realm and block ones
+	docker_settings: DockerSettings,
+	/// the kdd.yaml `container_engine:` (docker if none, overridden by the realm one and KDD_CONTAINER_ENGINE)
+	container_engine: Option<EngineKind>,
+	/// the kdd.yaml `hooks:` (the realm hooks are on the realm)
+	hooks: Hooks,
+	/// debounce and ignore globs of the kdd watch (from the kdd.yaml `watch:` section)
+	watch_config: WatchConfig,
 
 	pods_provider: PodsProvider,
 }
@@ -83,11 +126,18 @@ impl From<KddConfig> for Kdd {
 			system: config.system,
 			block_base_dir: config.block_base_dir,
 			image_tag: config.image_tag,
+			image_tags: config.image_tags,
 
 			realms: config.realms,
 			blocks: config.blocks,
 			builders: config.builders,
+			tasks: config.tasks,
 			versions: config.versions,
+			docker_policy: config.docker_policy,
+			docker_settings: config.docker_settings,
+			container_engine: config.container_engine,
+			hooks: config.hooks,
+			watch_config: config.watch_config,
 
 			pods_provider,
 		}
@@ -109,6 +159,11 @@ impl Kdd {
 		self.dir.join(path)
 	}
 
+	/// Returns the `.kdd/` directory of this kdd (for logs and other kdd generated files)
+	pub fn local_dir(&self) -> PathBuf {
+		self.dir.join(KDD_LOCAL_DIR)
+	}
+
 	/// Build path from kdd path or block path if starts with ./
 	pub fn get_rel_path(&self, block: &Block, path: &str) -> PathBuf {
 		// if starts with "./" then relative to block dir
@@ -120,11 +175,38 @@ impl Kdd {
 		}
 	}
 
-	pub fn image_tag(&self) -> String {
-		match &self.image_tag {
-			Some(image_tag) => image_tag.to_string(),
-			None => "default".to_string(),
+	/// Returns the kdd vars with the eventual realm vars merged on top of them
+	pub fn merged_vars(&self, realm: Option<&Realm>) -> HashMap<String, String> {
+		let mut merged_vars = self.vars.clone();
+		if let Some(realm) = realm {
+			for (name, val) in realm.vars.iter() {
+				merged_vars.insert(name.to_string(), val.to_string());
+			}
+		}
+		merged_vars
+	}
+
+	/// Returns the primary image tag for this realm (realm `image_tag`, or the kdd.yaml one, or "default").
+	/// Fails if the tag is empty or dangling (e.g., `dev-` for a `dev-{{git_sha}}` rendered without the sha)
+	pub fn image_tag(&self, realm: Option<&Realm>) -> Result<String, KddError> {
+		match realm.and_then(|r| r.image_tag.as_ref()).or(self.image_tag.as_ref()) {
+			Some(image_tag) if !is_valid_image_tag(image_tag) => Err(KddError::InvalidImageTag(image_tag.to_string())),
+			Some(image_tag) => Ok(image_tag.to_string()),
+			None => Ok("default".to_string()),
+		}
+	}
+
+	/// Returns all of the image tags for this realm, the primary first, and then the `image_tags`
+	/// (realm ones, or the kdd.yaml ones), e.g., `1.2.0`, `3f2a9c1`, `latest`
+	pub fn image_tags(&self, realm: Option<&Realm>) -> Result<Vec<String>, KddError> {
+		let extra_tags = realm.and_then(|r| r.image_tags.as_ref()).unwrap_or(&self.image_tags);
+		let mut tags = vec![self.image_tag(realm)?];
+		for tag in extra_tags.iter() {
+			if !tag.is_empty() && !tags.contains(tag) {
+				tags.push(tag.to_string());
+			}
 		}
+		Ok(tags)
 	}
 
 	pub fn image_name(&self, block: &Block) -> String {
@@ -189,7 +271,7 @@ impl Kdd {
 
 	fn k_get_json_items(entity_type: &str) -> Result<Vec<Value>, KddError> {
 		let args = &["get", entity_type, "-o", "json"];
-		let json = exec_to_stdout(None, "kubectl", args, false)?;
+		let json = query_to_stdout(None, "kubectl", args)?;
 		let mut json = serde_json::from_str::<Value>(&json)?;
 
 		match json["items"].take() {
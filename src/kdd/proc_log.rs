@@ -0,0 +1,139 @@
+////////////////////////////////////
+// kdd::proc_log - Prefixed console output and log files for the builder and docker child processes
+////
+
+use super::{error::KddError, Kdd};
+use crate::utils::{runner::is_dry_run, runner::ExtCmd, UtilsError};
+use std::{
+	collections::VecDeque,
+	fs::{create_dir_all, File},
+	io::{IsTerminal, Write},
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+use tokio::{
+	io::{AsyncBufReadExt, AsyncRead, BufReader},
+	process::Child,
+	task::JoinHandle,
+};
+
+const LOGS_DIR: &str = "logs";
+const TAIL_LINES: usize = 20;
+// ansi colors (cyan, green, yellow, magenta, blue and their bright versions)
+const PREFIX_COLORS: &[u8] = &[36, 32, 33, 35, 34, 96, 92, 93, 95, 94];
+
+/// The console prefix and log file of one step (builder or docker action) of a block.
+/// Each line is printed with a `[block:step]` prefix and written to `.kdd/logs/<block>/<step>.log` (not in dry-run)
+#[derive(Debug)]
+pub struct ProcLog {
+	prefix: String,
+	path: PathBuf,
+	file: Mutex<Option<File>>,
+	tail: Mutex<VecDeque<String>>,
+}
+
+impl ProcLog {
+	/// Create the log for a block step (truncate the eventual previous log file, no log file in dry-run)
+	pub fn new(kdd: &Kdd, block_name: &str, step_name: &str) -> Result<Arc<ProcLog>, KddError> {
+		let dir = kdd.local_dir().join(LOGS_DIR).join(block_name);
+		let path = dir.join(format!("{}.log", step_name));
+		let file = if is_dry_run() {
+			None
+		} else {
+			create_dir_all(&dir)?;
+			Some(File::create(&path)?)
+		};
+
+		let label = format!("{}:{}", block_name, step_name);
+		let prefix = if use_color() {
+			let color = PREFIX_COLORS[label.bytes().map(|b| b as usize).sum::<usize>() % PREFIX_COLORS.len()];
+			format!("\x1b[{}m[{}]\x1b[0m", color, label)
+		} else {
+			format!("[{}]", label)
+		};
+
+		Ok(Arc::new(ProcLog {
+			prefix,
+			path,
+			file: Mutex::new(file),
+			tail: Mutex::new(VecDeque::with_capacity(TAIL_LINES)),
+		}))
+	}
+
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Print the line with the prefix and append it to the log file
+	pub fn line(&self, line: &str) {
+		println!("{} {}", self.prefix, line);
+
+		if let Ok(Some(file)) = self.file.lock().as_deref_mut() {
+			// Note: a log write failure should not fail the build
+			let _ = writeln!(file, "{}", line);
+		}
+		if let Ok(mut tail) = self.tail.lock() {
+			if tail.len() == TAIL_LINES {
+				tail.pop_front();
+			}
+			tail.push_back(line.to_string());
+		}
+	}
+
+	/// Print the last lines of this log (used on failure, when the lines might have been interleaved with others)
+	pub fn print_tail(&self) {
+		println!("--- {} last lines of {} ", self.prefix, self.path.to_string_lossy());
+		if let Ok(tail) = self.tail.lock() {
+			for line in tail.iter() {
+				println!("    {}", line);
+			}
+		}
+		println!("--- /{} last lines", self.prefix);
+	}
+
+	/// Execute the command (blocking), with its stdout/stderr going through this log
+	pub fn exec_cmd_args<S: AsRef<str>>(&self, cwd: Option<&PathBuf>, cmd: &str, args: &[S], timeout: Option<Duration>) -> Result<(), KddError> {
+		let ext_cmd = ExtCmd::new(cmd, args).cwd(cwd.map(|p| p.as_path()));
+		if ext_cmd.dry_run() {
+			return Ok(());
+		}
+		self.line(&format!("> executing: {}", ext_cmd.cmd_line()));
+		match ext_cmd.exec_lines(timeout, |line| self.line(line)) {
+			Ok(_) => Ok(()),
+			Err(ex) => {
+				self.print_tail();
+				match ex {
+					UtilsError::ExecTimeout(cmd, secs) => Err(KddError::ExecTimeout(cmd, secs)),
+					ex => Err(ex.into()),
+				}
+			}
+		}
+	}
+}
+
+/// Pipe the stdout and stderr of a spawned (tokio) child into the log.
+/// Returns the reader handles, which are done when the child closes its outputs.
+pub fn pipe_child(child: &mut Child, log: &Arc<ProcLog>) -> Vec<JoinHandle<()>> {
+	let mut handles = Vec::new();
+	if let Some(stdout) = child.stdout.take() {
+		handles.push(pipe_reader(stdout, log.clone()));
+	}
+	if let Some(stderr) = child.stderr.take() {
+		handles.push(pipe_reader(stderr, log.clone()));
+	}
+	handles
+}
+
+fn pipe_reader<R: AsyncRead + Unpin + Send + 'static>(reader: R, log: Arc<ProcLog>) -> JoinHandle<()> {
+	tokio::spawn(async move {
+		let mut lines = BufReader::new(reader).lines();
+		while let Ok(Some(line)) = lines.next_line().await {
+			log.line(&line);
+		}
+	})
+}
+
+fn use_color() -> bool {
+	std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal()
+}
@@ -2,16 +2,14 @@
 // kdd::provider - cloud specific adapters
 ////
 
-use super::{error::KddError, Block, Realm};
-use crate::utils::{exec_cmd_args, exec_to_stdout};
+use super::prune::{parse_timestamp, ImageTag};
+use super::registry::{registry_delete_manifest, registry_image_tags};
+use super::{engine::ContainerEngine, error::KddError, Block, Realm};
+use crate::utils::{exec_cmd_args, exec_to_stdout, query_to_stdout, runner::ExtCmd};
 
 use core::fmt::Debug;
 use serde_json::Value;
-use std::{
-	collections::HashSet,
-	io::Write,
-	process::{Command, Stdio},
-};
+use std::collections::HashSet;
 use strum_macros::Display;
 
 /// Default empty implementation for cloud/cluster providers.
@@ -29,7 +27,27 @@ pub trait Provider {
 		Ok(())
 	}
 
-	fn docker_auth(&self, _realm: &Realm) -> Result<(), KddError> {
+	fn docker_auth(&self, _realm: &Realm, _engine: &dyn ContainerEngine) -> Result<(), KddError> {
+		Ok(())
+	}
+
+	/// True for the local clusters side-loading the images (no registry, e.g., kind, k3d, minikube)
+	fn is_side_load(&self) -> bool {
+		false
+	}
+
+	/// Load the local image into the realm cluster (for the side-load providers, instead of a push)
+	fn side_load_image(&self, _realm: &Realm, _image_uri: &str) -> Result<ExtCmd, KddError> {
+		Err(KddError::ProviderNoSideLoad)
+	}
+
+	/// The tags of this realm registry repository (for `kdd dprune`), None if not supported by the provider
+	fn registry_tags(&self, _realm: &Realm, _repo: &str) -> Result<Option<Vec<ImageTag>>, KddError> {
+		Ok(None)
+	}
+
+	/// Delete these tags of this realm registry repository (for `kdd dprune`)
+	fn delete_registry_tags(&self, _realm: &Realm, _repo: &str, _tags: &[&ImageTag]) -> Result<(), KddError> {
 		Ok(())
 	}
 }
@@ -43,11 +61,23 @@ pub struct GcpProvider;
 #[derive(Debug)]
 pub struct CommonProvider;
 
+#[derive(Debug)]
+pub struct KindProvider;
+
+#[derive(Debug)]
+pub struct K3dProvider;
+
+#[derive(Debug)]
+pub struct MinikubeProvider;
+
 #[derive(Debug, Display)]
 pub enum RealmProvider {
 	Aws(AwsProvider),
 	Gcp(GcpProvider),
 	Common(CommonProvider),
+	Kind(KindProvider),
+	K3d(K3dProvider),
+	Minikube(MinikubeProvider),
 }
 
 impl Debug for dyn Provider {
@@ -78,25 +108,62 @@ impl Provider for AwsProvider {
 		Ok(())
 	}
 
-	fn docker_auth(&self, realm: &Realm) -> Result<(), KddError> {
+	fn docker_auth(&self, realm: &Realm, engine: &dyn ContainerEngine) -> Result<(), KddError> {
 		if let Some(registry) = &realm.registry {
 			// get the password
 			let pwd = exec_to_stdout(None, "aws", &["ecr", "get-login-password", "--profile", &realm.profile()], false)?;
 
-			// execute the login
-			let cmd = "docker";
-			let args = &["login", "--username", "AWS", "--password-stdin", registry];
-			println!("> executing: {} {} (with previous command result as stdin)", cmd, args.join(" "));
-			let mut proc = Command::new(&cmd);
-			proc.args(args);
-			proc.stdin(Stdio::piped());
-			let mut child = proc.spawn()?;
-			child.stdin.as_ref().unwrap().write_all(pwd.as_bytes())?;
-			child.wait()?;
+			// execute the login (with the password as stdin)
+			ExtCmd::new(engine.cmd(), &engine.login_args("AWS", registry)).exec_with_stdin(&pwd)?;
 		}
 
 		Ok(())
 	}
+
+	fn registry_tags(&self, realm: &Realm, repo: &str) -> Result<Option<Vec<ImageTag>>, KddError> {
+		// aws ecr describe-images --repository-name cstar-agent --profile jc-root
+		let args = [
+			"ecr",
+			"describe-images",
+			"--repository-name",
+			repo_name(repo),
+			"--profile",
+			&realm.profile(),
+		];
+		let json = query_to_stdout(None, "aws", &args)?;
+		let json = serde_json::from_str::<Value>(&json).map_err(|_| KddError::RegistryRequestFailed(repo.to_string(), json.trim().to_string()))?;
+
+		let mut tags = Vec::new();
+		for image in json["imageDetails"].as_array().into_iter().flatten() {
+			// Note: imagePushedAt is epoch seconds, or a date string depending on the aws cli config
+			let created = match &image["imagePushedAt"] {
+				Value::Number(num) => num.as_f64().map(|secs| secs as u64),
+				Value::String(val) => parse_timestamp(val),
+				_ => None,
+			};
+			let digest = image["imageDigest"].as_str().map(|d| d.to_string());
+			for tag in image["imageTags"].as_array().into_iter().flatten().filter_map(|v| v.as_str()) {
+				tags.push(ImageTag {
+					tag: tag.to_string(),
+					created,
+					digest: digest.clone(),
+				});
+			}
+		}
+		Ok(Some(tags))
+	}
+
+	fn delete_registry_tags(&self, realm: &Realm, repo: &str, tags: &[&ImageTag]) -> Result<(), KddError> {
+		// aws ecr batch-delete-image --repository-name cstar-agent --image-ids imageTag=1.0.0 imageTag=1.0.1 --profile jc-root
+		let mut args: Vec<String> = ["ecr", "batch-delete-image", "--repository-name", repo_name(repo), "--image-ids"]
+			.iter()
+			.map(|s| s.to_string())
+			.collect();
+		args.extend(tags.iter().map(|t| format!("imageTag={}", t.tag)));
+		args.extend(["--profile".to_string(), realm.profile()]);
+		ExtCmd::new("aws", &args).exec()?;
+		Ok(())
+	}
 }
 
 impl AwsProvider {
@@ -105,7 +172,7 @@ impl AwsProvider {
 		let profile = realm.profile();
 
 		// aws ecr describe-repositories --profile jc-root
-		let json = exec_to_stdout(None, "aws", &["ecr", "describe-repositories", "--profile", &profile], false)?;
+		let json = query_to_stdout(None, "aws", &["ecr", "describe-repositories", "--profile", &profile])?;
 		let json = serde_json::from_str::<Value>(&json).map_err(|ex| KddError::AwsEcrDescribeRepositoriesFailed(ex.to_string()))?;
 		if let Some(reps) = json["repositories"].as_array() {
 			for rep in reps {
@@ -121,9 +188,123 @@ impl AwsProvider {
 // endregion: Aws Provider
 
 // region:    Gcp Provider
-impl Provider for GcpProvider {}
+impl Provider for GcpProvider {
+	fn registry_tags(&self, _realm: &Realm, repo: &str) -> Result<Option<Vec<ImageTag>>, KddError> {
+		// Note: artifact registry (e.g., `us-west1-docker.pkg.dev/project/repo/cstar-agent`), one item per image version
+		let args = ["artifacts", "docker", "images", "list", repo, "--include-tags", "--format=json"];
+		let json = query_to_stdout(None, "gcloud", &args)?;
+		let json = serde_json::from_str::<Value>(&json).map_err(|_| KddError::RegistryRequestFailed(repo.to_string(), json.trim().to_string()))?;
+
+		let mut tags = Vec::new();
+		for image in json.as_array().into_iter().flatten() {
+			let created = image["createTime"].as_str().and_then(parse_timestamp);
+			let digest = image["version"].as_str().map(|d| d.to_string());
+			// Note: `tags` is a comma separated string (or an array on some gcloud versions)
+			let image_tags: Vec<String> = match &image["tags"] {
+				Value::String(val) => val.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect(),
+				Value::Array(vals) => vals.iter().filter_map(|v| v.as_str()).map(|t| t.to_string()).collect(),
+				_ => Vec::new(),
+			};
+			for tag in image_tags {
+				tags.push(ImageTag {
+					tag,
+					created,
+					digest: digest.clone(),
+				});
+			}
+		}
+		Ok(Some(tags))
+	}
+
+	fn delete_registry_tags(&self, _realm: &Realm, repo: &str, tags: &[&ImageTag]) -> Result<(), KddError> {
+		for tag in tags {
+			let tag_uri = format!("{}:{}", repo, tag.tag);
+			ExtCmd::new("gcloud", &["artifacts", "docker", "tags", "delete", &tag_uri, "--quiet"]).exec()?;
+		}
+		Ok(())
+	}
+}
 // endregion: Gcp Provider
 
 // region:    Common Provider
-impl Provider for CommonProvider {}
+impl Provider for CommonProvider {
+	/// Only for the local registry (registry v2 api, plain http)
+	fn registry_tags(&self, realm: &Realm, repo: &str) -> Result<Option<Vec<ImageTag>>, KddError> {
+		if !realm.is_local_registry() {
+			return Ok(None);
+		}
+		Ok(Some(registry_image_tags(repo)?))
+	}
+
+	// Note: the registry api deletes manifests (not tags), so, all of the tags of this image
+	//       (the prune never removes a tag sharing its digest with a kept one)
+	fn delete_registry_tags(&self, _realm: &Realm, repo: &str, tags: &[&ImageTag]) -> Result<(), KddError> {
+		let mut deleted: HashSet<&str> = HashSet::new();
+		for digest in tags.iter().filter_map(|t| t.digest.as_deref()) {
+			if deleted.insert(digest) {
+				registry_delete_manifest(repo, digest)?;
+			}
+		}
+		Ok(())
+	}
+}
 // endregion: Common Provider
+
+// region:    Kind Provider
+impl Provider for KindProvider {
+	fn is_side_load(&self) -> bool {
+		true
+	}
+
+	// e.g., kind load docker-image localhost:5000/cstar-agent:1.0.0 --name dev (for the `kind-dev` context)
+	fn side_load_image(&self, realm: &Realm, image_uri: &str) -> Result<ExtCmd, KddError> {
+		let cluster = cluster_name(realm, "kind-", "kind");
+		Ok(ExtCmd::new("kind", &["load", "docker-image", image_uri, "--name", &cluster]))
+	}
+}
+// endregion: Kind Provider
+
+// region:    K3d Provider
+impl Provider for K3dProvider {
+	fn is_side_load(&self) -> bool {
+		true
+	}
+
+	// e.g., k3d image import localhost:5000/cstar-agent:1.0.0 -c dev (for the `k3d-dev` context)
+	fn side_load_image(&self, realm: &Realm, image_uri: &str) -> Result<ExtCmd, KddError> {
+		let cluster = cluster_name(realm, "k3d-", "k3s-default");
+		Ok(ExtCmd::new("k3d", &["image", "import", image_uri, "-c", &cluster]))
+	}
+}
+// endregion: K3d Provider
+
+// region:    Minikube Provider
+impl Provider for MinikubeProvider {
+	fn is_side_load(&self) -> bool {
+		true
+	}
+
+	// e.g., minikube image load localhost:5000/cstar-agent:1.0.0 -p minikube (the context is the minikube profile)
+	fn side_load_image(&self, realm: &Realm, image_uri: &str) -> Result<ExtCmd, KddError> {
+		let profile = cluster_name(realm, "", "minikube");
+		Ok(ExtCmd::new("minikube", &["image", "load", image_uri, "-p", &profile]))
+	}
+}
+// endregion: Minikube Provider
+
+// region:    Utils
+/// The repository name of this image repo (e.g., `<account>.dkr.ecr.us-west-2.amazonaws.com/cstar-agent` -> `cstar-agent`)
+fn repo_name(repo: &str) -> &str {
+	repo.rsplit('/').next().unwrap_or(repo)
+}
+
+/// The local cluster name from the realm context (e.g., `kind-dev` -> `dev`)
+fn cluster_name(realm: &Realm, ctx_prefix: &str, default: &str) -> String {
+	realm
+		.context()
+		.map(|ctx| ctx.strip_prefix(ctx_prefix).unwrap_or(ctx))
+		.filter(|name| !name.is_empty())
+		.unwrap_or(default)
+		.to_string()
+}
+// endregion: Utils
@@ -0,0 +1,268 @@
+////////////////////////////////////
+// kdd::prune - Remove the old image tags of the blocks, locally and in the realm registry (`kdd dprune`)
+////
+
+use super::{error::KddError, image_state::image_repo, realm::Realm, run_policy::parse_duration, Kdd};
+use crate::utils::query_to_stdout;
+use crate::utils::runner::{is_dry_run, ExtCmd};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::fs::{read_dir, read_to_string};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const PRUNE_KEEP_DEFAULT: usize = 5;
+
+/// An image tag of a repository (local or in a registry)
+#[derive(Debug, Clone)]
+pub struct ImageTag {
+	pub tag: String,
+	/// creation (or push) time, in unix seconds (when known)
+	pub created: Option<u64>,
+	/// image digest (or id), to not remove a tag of an image still tagged with a kept tag
+	pub digest: Option<String>,
+}
+
+/// The `kdd dprune` options
+#[derive(Debug, Default)]
+pub struct PruneOptions {
+	/// The number of most recent tags to keep (default 5)
+	pub keep: Option<usize>,
+	/// Only remove the tags older than this
+	pub older_than: Option<Duration>,
+}
+
+//// PruneOptions Builder(s)
+impl PruneOptions {
+	/// `older_than` as `30d`, `12h`, ... (see the docker `timeout:`)
+	pub fn new(keep: Option<usize>, older_than: Option<&str>) -> Result<PruneOptions, KddError> {
+		let older_than = match older_than {
+			Some(val) => Some(parse_duration(val).ok_or_else(|| KddError::InvalidPruneOlderThan(val.to_string()))?),
+			None => None,
+		};
+		Ok(PruneOptions { keep, older_than })
+	}
+}
+
+//// Kdd Prune Methods
+impl Kdd {
+	/// Remove the old tags of the selected docker blocks, locally, and in the realm registry (via the realm provider).
+	/// The tags referenced by the rendered k8s files (`.out/<realm>`) and the current image tags are never removed.
+	pub fn d_prune(&self, realm: Option<&Realm>, names: Option<&[&str]>, options: &PruneOptions) -> Result<(), KddError> {
+		let (blocks, _) = self.blocks_for_names(names, true)?;
+		let rendered = self.rendered_k8s_contents();
+		let protected = self.protected_tags()?;
+
+		for block in blocks {
+			// -- local images (the local and realm repositories)
+			let engine = self.engine(realm)?;
+			let mut repos = vec![image_repo(&self.image_uri(block, None)?).to_string()];
+			if let Some(realm) = realm {
+				let repo = image_repo(&self.image_uri(block, Some(realm))?).to_string();
+				if !repos.contains(&repo) {
+					repos.push(repo);
+				}
+			}
+			for repo in repos.iter() {
+				let out = query_to_stdout(None, engine.cmd(), &engine.list_tags_args(repo))?;
+				let tags: Vec<ImageTag> = out.lines().filter_map(parse_local_tag_line).collect();
+				let to_remove = prune_tags(repo, &tags, &protected, &rendered, options);
+				print_prune("local", repo, &tags, &to_remove);
+				for tag in to_remove {
+					let image_uri = format!("{}:{}", repo, tag.tag);
+					if let Err(ex) = ExtCmd::new(engine.cmd(), &engine.rmi_args(&image_uri)).exec() {
+						println!("KDD WARNING - fail to remove image {}. Cause: {}", image_uri, ex);
+					}
+				}
+			}
+
+			// -- realm registry (side-loaded realms have no registry)
+			let Some(realm) = realm.filter(|r| !r.is_side_load()) else {
+				continue;
+			};
+			let repo = image_repo(&self.image_uri(block, Some(realm))?).to_string();
+			match realm.provider().registry_tags(realm, &repo) {
+				Ok(Some(tags)) => {
+					let to_remove = prune_tags(&repo, &tags, &protected, &rendered, options);
+					print_prune(&format!("realm {}", realm.name), &repo, &tags, &to_remove);
+					if !to_remove.is_empty() {
+						realm.provider().delete_registry_tags(realm, &repo, &to_remove)?;
+					}
+				}
+				Ok(None) => println!(
+					"KDD WARNING - realm {} registry tags listing not supported, skipping {}",
+					realm.name, repo
+				),
+				Err(ex) => println!(
+					"KDD WARNING - fail to list the realm {} registry tags of {}. Cause: {}",
+					realm.name, repo, ex
+				),
+			}
+		}
+
+		Ok(())
+	}
+
+	/// The current image tags of all realms (never pruned)
+	fn protected_tags(&self) -> Result<HashSet<String>, KddError> {
+		let mut tags: HashSet<String> = self.image_tags(None)?.into_iter().collect();
+		for realm in self.realms() {
+			tags.extend(self.image_tags(Some(realm))?);
+		}
+		Ok(tags)
+	}
+
+	/// The contents of all of the realms rendered k8s files
+	fn rendered_k8s_contents(&self) -> Vec<String> {
+		let mut contents = Vec::new();
+		for realm in self.realms() {
+			let Ok(entries) = read_dir(realm.k8s_out_dir()) else { continue };
+			for entry in entries.flatten() {
+				let path = entry.path();
+				if path.is_file() {
+					if let Ok(content) = read_to_string(&path) {
+						contents.push(content);
+					}
+				}
+			}
+		}
+		contents
+	}
+}
+
+// region:    Prune Logic
+/// Returns the tags to remove: after the `keep` most recent ones, older than `older_than` (if any),
+/// and not protected, referenced by a rendered k8s file, or sharing its image with a kept tag.
+pub fn prune_tags<'a>(
+	repo: &str,
+	tags: &'a [ImageTag],
+	protected: &HashSet<String>,
+	rendered: &[String],
+	options: &PruneOptions,
+) -> Vec<&'a ImageTag> {
+	let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+	let keep = options.keep.unwrap_or(PRUNE_KEEP_DEFAULT);
+
+	// most recent first (unknown creation time last)
+	let mut sorted: Vec<&ImageTag> = tags.iter().filter(|t| t.tag != "<none>").collect();
+	sorted.sort_by_key(|t| Reverse(t.created));
+
+	let (mut kept, mut to_remove): (Vec<&ImageTag>, Vec<&ImageTag>) = (Vec::new(), Vec::new());
+	for (idx, tag) in sorted.into_iter().enumerate() {
+		let old_enough = match (options.older_than, tag.created) {
+			(Some(older_than), Some(created)) => now.saturating_sub(created) > older_than.as_secs(),
+			(Some(_), None) => false,
+			(None, _) => true,
+		};
+		let referenced = protected.contains(&tag.tag) || rendered.iter().any(|content| references(content, repo, tag));
+		if idx < keep || !old_enough || referenced {
+			kept.push(tag);
+		} else {
+			to_remove.push(tag);
+		}
+	}
+
+	// do not remove a tag of an image still tagged with a kept tag (e.g., `latest`)
+	to_remove.retain(|tag| match &tag.digest {
+		Some(digest) => !kept.iter().any(|k| k.digest.as_ref() == Some(digest)),
+		None => true,
+	});
+	to_remove
+}
+
+/// True if this k8s content references this repo tag (e.g., `cstar-agent:1.0.0`, not `cstar-agent:1.0.0-rc`),
+/// or its digest
+fn references(content: &str, repo: &str, tag: &ImageTag) -> bool {
+	if let Some(digest) = &tag.digest {
+		if digest.starts_with("sha256:") && content.contains(digest.as_str()) {
+			return true;
+		}
+	}
+	// the image name (the last repo path) is enough (registry agnostic)
+	let image_name = repo.rsplit('/').next().unwrap_or(repo);
+	let needle = format!("{}:{}", image_name, tag.tag);
+	content.match_indices(&needle).any(|(idx, _)| {
+		let next = content[idx + needle.len()..].chars().next();
+		!matches!(next, Some(c) if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_')
+	})
+}
+// endregion: Prune Logic
+
+// region:    Utils
+fn print_prune(location: &str, repo: &str, tags: &[ImageTag], to_remove: &[&ImageTag]) {
+	if tags.is_empty() {
+		return;
+	}
+	let prefix = if is_dry_run() { "(dry-run) would remove" } else { "remove" };
+	println!("--- {} {} - {} tags, {} to remove", location, repo, tags.len(), to_remove.len());
+	for tag in to_remove {
+		println!("    {} {}:{}", prefix, repo, tag.tag);
+	}
+}
+
+/// Parse a `<tag>\t<created at>\t<id>` line of the engine image listing
+fn parse_local_tag_line(line: &str) -> Option<ImageTag> {
+	let mut parts = line.trim().split('\t');
+	let tag = parts.next()?.trim();
+	if tag.is_empty() {
+		return None;
+	}
+	Some(ImageTag {
+		tag: tag.to_string(),
+		created: parts.next().and_then(parse_timestamp),
+		digest: parts.next().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+	})
+}
+
+/// Parse a timestamp to unix seconds, e.g., `2024-05-01T10:00:00.123Z`, `2024-05-01T10:00:00-08:00`,
+/// `2024-05-01 10:00:00 +0000 UTC`, or `1714557600.0`
+pub fn parse_timestamp(val: &str) -> Option<u64> {
+	let val = val.trim();
+	if let Ok(secs) = val.parse::<f64>() {
+		return Some(secs as u64);
+	}
+
+	let num = |s: &str| s.parse::<i64>().ok();
+	let (date, rest) = (val.get(..10)?, val.get(11..)?);
+	let mut date_parts = date.split('-');
+	let (y, m, d) = (num(date_parts.next()?)?, num(date_parts.next()?)?, num(date_parts.next()?)?);
+	let time = rest.get(..8)?;
+	let mut time_parts = time.split(':');
+	let (hh, mm, ss) = (num(time_parts.next()?)?, num(time_parts.next()?)?, num(time_parts.next()?)?);
+
+	// the eventual offset (after the fractional seconds), e.g., `Z`, `-08:00`, ` +0000 UTC`
+	let offset = rest[8..]
+		.trim_start_matches(|c: char| c == '.' || c.is_ascii_digit())
+		.trim_start();
+	let offset_secs = match offset.chars().next() {
+		Some(sign @ ('+' | '-')) => {
+			let digits: String = offset[1..].chars().filter(|c| c.is_ascii_digit()).take(4).collect();
+			let secs = num(digits.get(..2)?)? * 3600 + num(digits.get(2..4).unwrap_or("0"))? * 60;
+			if sign == '+' {
+				secs
+			} else {
+				-secs
+			}
+		}
+		_ => 0,
+	};
+
+	let secs = days_from_civil(y, m, d) * 86400 + hh * 3600 + mm * 60 + ss - offset_secs;
+	u64::try_from(secs).ok()
+}
+
+/// The days since 1970-01-01 of this (proleptic gregorian) date
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+	let y = if m <= 2 { y - 1 } else { y };
+	let era = if y >= 0 { y } else { y - 399 } / 400;
+	let yoe = y - era * 400;
+	let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + d - 1;
+	let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+	era * 146097 + doe - 719468
+}
+// endregion: Utils
+
+// region:    Tests
+#[cfg(test)]
+#[path = "../_test/kdd_prune.rs"]
+mod tests;
+// endregion: Tests
@@ -3,10 +3,14 @@
 ////
 
 use super::{
+	docker_settings::DockerSettings,
+	engine::EngineKind,
 	error::KddError,
-	provider::{AwsProvider, CommonProvider, GcpProvider, Provider, RealmProvider},
+	hook::Hooks,
+	provider::{AwsProvider, CommonProvider, GcpProvider, K3dProvider, KindProvider, MinikubeProvider, Provider, RealmProvider},
 	Kdd,
 };
+use crate::utils::runner::is_dry_run;
 use crate::utils::yamls::{as_bool, as_string, as_strings, to_string};
 use std::{
 	collections::{HashMap, HashSet},
@@ -23,6 +27,12 @@ const REALM_KEY_PROJECT: &str = "project"; // for GKE
 const REALM_KEY_REGISTRY: &str = "registry"; // must on AWS (inferred for gke and docker-dekstop)
 const REALM_KEY_PROFILE: &str = "profile"; // for AWS
 const REALM_KEY_CONFIGURATIONS: &str = "default_configurations"; // for AWS
+const REALM_KEY_HOOKS: &str = "hooks";
+const REALM_KEY_DOCKER: &str = "docker";
+const REALM_KEY_IMAGE_TAG: &str = "image_tag"; // override the kdd.yaml ones (e.g., `dev-{{git_sha}}`)
+const REALM_KEY_IMAGE_TAGS: &str = "image_tags";
+const REALM_KEY_CONTAINER_ENGINE: &str = "container_engine";
+const REALM_KEY_PROVIDER: &str = "provider"; // explicit provider (otherwise, from the context)
 
 //// Realm Struct
 #[derive(Debug)]
@@ -34,6 +44,14 @@ pub struct Realm {
 	pub profile: Option<String>,
 	pub project: Option<String>,
 	pub default_configurations: Option<Vec<String>>,
+	pub image_tag: Option<String>,
+	pub image_tags: Option<Vec<String>>,
+	/// realm `hooks:` (run after the kdd.yaml ones)
+	pub hooks: Hooks,
+	/// realm `docker:` build settings (override the kdd.yaml ones, e.g., platform, args)
+	pub docker: DockerSettings,
+	/// realm `container_engine:` (override the kdd.yaml one)
+	pub container_engine: Option<EngineKind>,
 	provider: RealmProvider,
 	yaml_dirs: Vec<PathBuf>,
 	context: Option<String>,
@@ -46,21 +64,52 @@ impl Realm {
 			Ok(RealmProvider::Aws(AwsProvider))
 		} else if ctx.starts_with("gke") {
 			Ok(RealmProvider::Gcp(GcpProvider))
+		} else if ctx.starts_with("kind-") {
+			Ok(RealmProvider::Kind(KindProvider))
+		} else if ctx.starts_with("k3d-") {
+			Ok(RealmProvider::K3d(K3dProvider))
+		} else if ctx.starts_with("minikube") {
+			Ok(RealmProvider::Minikube(MinikubeProvider))
 		} else {
 			Ok(RealmProvider::Common(CommonProvider))
 		}
 	}
 
+	/// The explicit realm `provider:` (e.g., `kind`, for a context without the `kind-` prefix)
+	pub fn provider_from_name(realm_name: &str, name: &str) -> Result<RealmProvider, KddError> {
+		match name.to_lowercase().as_str() {
+			"aws" => Ok(RealmProvider::Aws(AwsProvider)),
+			"gcp" => Ok(RealmProvider::Gcp(GcpProvider)),
+			"kind" => Ok(RealmProvider::Kind(KindProvider)),
+			"k3d" => Ok(RealmProvider::K3d(K3dProvider)),
+			"minikube" => Ok(RealmProvider::Minikube(MinikubeProvider)),
+			"common" => Ok(RealmProvider::Common(CommonProvider)),
+			_ => Err(KddError::RealmProviderUnknown(realm_name.to_string(), name.to_string())),
+		}
+	}
+
 	pub fn provider(&self) -> &dyn Provider {
 		match &self.provider {
 			RealmProvider::Aws(p) => p as &dyn Provider,
 			RealmProvider::Gcp(p) => p as &dyn Provider,
 			RealmProvider::Common(p) => p as &dyn Provider,
+			RealmProvider::Kind(p) => p as &dyn Provider,
+			RealmProvider::K3d(p) => p as &dyn Provider,
+			RealmProvider::Minikube(p) => p as &dyn Provider,
 		}
 	}
 
+	pub fn context(&self) -> Option<&str> {
+		self.context.as_deref()
+	}
+
+	/// True if the images are side-loaded into the local cluster (kind, k3d, minikube) rather than pushed
+	pub fn is_side_load(&self) -> bool {
+		self.registry.is_none() && self.provider().is_side_load()
+	}
+
 	pub fn is_local_registry(&self) -> bool {
-		// return true if no registry or registry is localhost or 127.0.0.1
+		// return true if no registry or registry is localhost or 127.0.0.1 (or side-loaded, without registry)
 		self.registry
 			.as_ref()
 			.map(|registry| registry.contains("localhost") || registry.contains("127.0.0.1"))
@@ -121,6 +170,14 @@ impl Realm {
 		yaml_paths
 	}
 
+	/// Returns the names (file stems) of all of the realm k8s files
+	pub fn k8s_names(&self) -> Vec<String> {
+		self.k8s_files(None)
+			.iter()
+			.filter_map(|path| path.file_stem().and_then(|s| s.to_str()).map(|s| s.to_string()))
+			.collect()
+	}
+
 	pub fn k8s_out_dir(&self) -> PathBuf {
 		// Note: There is always at least one yaml_dir, per parse_realm logic
 		let yaml_dir = &self.yaml_dirs[0];
@@ -141,7 +198,10 @@ impl Realm {
 impl Realm {
 	pub fn from_yaml(kdd_dir: &PathBuf, name: &str, yaml: &Yaml) -> Result<Realm, KddError> {
 		let ctx = as_string(yaml, REALM_KEY_CONTEXT).ok_or_else(|| KddError::MissingRealmContext(name.to_string()))?;
-		let provider = Realm::provider_from_ctx(&ctx)?;
+		let provider = match as_string(yaml, REALM_KEY_PROVIDER) {
+			Some(provider) => Realm::provider_from_name(name, &provider)?,
+			None => Realm::provider_from_ctx(&ctx)?,
+		};
 
 		// get the string or strings values as an array of string
 		let yaml_dirs = as_string(yaml, REALM_KEY_YAML_DIR)
@@ -185,6 +245,11 @@ impl Realm {
 			registry: as_string(yaml, REALM_KEY_REGISTRY),
 			profile: as_string(yaml, REALM_KEY_PROFILE),
 			default_configurations: as_strings(yaml, REALM_KEY_CONFIGURATIONS),
+			image_tag: as_string(yaml, REALM_KEY_IMAGE_TAG),
+			image_tags: as_strings(yaml, REALM_KEY_IMAGE_TAGS),
+			hooks: Hooks::from_yaml(&yaml[REALM_KEY_HOOKS]),
+			docker: DockerSettings::from_realm_yaml(name, &yaml[REALM_KEY_DOCKER]),
+			container_engine: EngineKind::from_setting(as_string(yaml, REALM_KEY_CONTAINER_ENGINE)),
 		})
 	}
 }
@@ -216,14 +281,17 @@ impl Kdd {
 						let ctxs = self.k_list_context()?;
 						let ctxs_set: HashSet<_> = HashSet::from_iter(ctxs);
 
+						// Note: in dry-run, no confirmation, as nothing gets created
 						if !ctxs_set.contains(ctx) {
-							println!("Kubernetes context {} does not exist. Do you want to create it and set it? (YES to continue, anything else to cancel)", ctx);
-							let mut guess = String::new();
-							stdin().read_line(&mut guess).expect("Failed to read line");
-
-							if guess.trim() != "YES" {
-								println!("Canceling kubernetes context creation");
-								return Ok(());
+							if !is_dry_run() {
+								println!("Kubernetes context {} does not exist. Do you want to create it and set it? (YES to continue, anything else to cancel)", ctx);
+								let mut guess = String::new();
+								stdin().read_line(&mut guess).expect("Failed to read line");
+
+								if guess.trim() != "YES" {
+									println!("Canceling kubernetes context creation");
+									return Ok(());
+								}
 							}
 							self.k_create_context(&ctx);
 							self.k_set_context(&ctx);
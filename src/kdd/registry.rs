@@ -0,0 +1,269 @@
+////////////////////////////////////
+// kdd::registry - The local registry container (`kdd registry start|stop|status|list`)
+////
+
+use super::{error::KddError, prune::parse_timestamp, prune::ImageTag, realm::Realm, Kdd};
+use crate::utils::query_to_stdout;
+use crate::utils::runner::{is_dry_run, ExtCmd};
+use serde_json::Value;
+use std::io::{stdin, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::thread::sleep;
+use std::time::Duration;
+
+/// The default registry of the local realms (e.g., docker-desktop)
+pub const LOCAL_REGISTRY: &str = "localhost:5000";
+
+const REGISTRY_CONTAINER: &str = "kdd-registry";
+const REGISTRY_VOLUME: &str = "kdd-registry-data";
+const REGISTRY_IMAGE: &str = "registry:2";
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(3);
+
+//// Kdd Registry Methods
+impl Kdd {
+	/// Returns the local registry host (e.g., `localhost:5000`) of this realm (the realm registry if local)
+	pub fn local_registry(&self, realm: Option<&Realm>) -> String {
+		let registry = realm
+			.filter(|r| r.is_local_registry())
+			.and_then(|r| r.registry.as_deref())
+			.unwrap_or(LOCAL_REGISTRY);
+		// only the host:port (e.g., `localhost:5000/some/path/` -> `localhost:5000`)
+		registry.trim_end_matches('/').split('/').next().unwrap_or(registry).to_string()
+	}
+
+	/// Start the local registry container (with its persistent volume), created if needed
+	pub fn registry_start(&self, realm: Option<&Realm>) -> Result<(), KddError> {
+		let engine = self.engine(realm)?;
+		let registry = self.local_registry(realm);
+
+		match self.registry_container_status(realm)?.as_deref() {
+			Some("running") => println!("Local registry {} already running (container {})", registry, REGISTRY_CONTAINER),
+			Some(_) => {
+				ExtCmd::new(engine.cmd(), &["start", REGISTRY_CONTAINER]).exec()?;
+				println!("Local registry {} started (container {})", registry, REGISTRY_CONTAINER);
+			}
+			None => {
+				let port = registry.rsplit_once(':').map(|(_, port)| port).unwrap_or("5000");
+				let publish = format!("{}:5000", port);
+				let volume = format!("{}:/var/lib/registry", REGISTRY_VOLUME);
+				// Note: delete enabled for `kdd dprune`
+				let args = [
+					"run",
+					"-d",
+					"--restart=always",
+					"-e",
+					"REGISTRY_STORAGE_DELETE_ENABLED=true",
+					"-p",
+					&publish,
+					"-v",
+					&volume,
+					"--name",
+					REGISTRY_CONTAINER,
+					REGISTRY_IMAGE,
+				];
+				ExtCmd::new(engine.cmd(), &args).exec()?;
+				println!(
+					"Local registry {} created and started (container {}, volume {})",
+					registry, REGISTRY_CONTAINER, REGISTRY_VOLUME
+				);
+			}
+		}
+		Ok(())
+	}
+
+	/// Stop the local registry container (the container and its volume are kept)
+	pub fn registry_stop(&self, realm: Option<&Realm>) -> Result<(), KddError> {
+		let engine = self.engine(realm)?;
+		match self.registry_container_status(realm)?.as_deref() {
+			Some("running") => {
+				ExtCmd::new(engine.cmd(), &["stop", REGISTRY_CONTAINER]).exec()?;
+				println!("Local registry stopped (container {})", REGISTRY_CONTAINER);
+			}
+			Some(status) => println!("Local registry not running (container {} {})", REGISTRY_CONTAINER, status),
+			None => println!("No local registry container ({})", REGISTRY_CONTAINER),
+		}
+		Ok(())
+	}
+
+	/// Print the local registry container status and if the registry is reachable
+	pub fn registry_status(&self, realm: Option<&Realm>) -> Result<(), KddError> {
+		let registry = self.local_registry(realm);
+		let status = self.registry_container_status(realm)?;
+		println!("registry:  {}", registry);
+		println!("container: {} ({})", REGISTRY_CONTAINER, status.as_deref().unwrap_or("not created"));
+		match registry_get(&registry, "/v2/_catalog") {
+			Ok(json) => {
+				let count = json["repositories"].as_array().map(|v| v.len()).unwrap_or(0);
+				println!("reachable: yes ({} repositories)", count);
+			}
+			Err(ex) => println!("reachable: no ({})", ex),
+		}
+		Ok(())
+	}
+
+	/// Print the repositories of the local registry with their tags
+	pub fn registry_list(&self, realm: Option<&Realm>) -> Result<(), KddError> {
+		let registry = self.local_registry(realm);
+		let catalog = registry_get(&registry, "/v2/_catalog")?;
+
+		println!("{: <40}TAGS", "REPOSITORY");
+		for repo in catalog["repositories"]
+			.as_array()
+			.into_iter()
+			.flatten()
+			.filter_map(|v| v.as_str())
+		{
+			let tags = registry_get(&registry, &format!("/v2/{}/tags/list", repo))?;
+			let tags: Vec<&str> = tags["tags"]
+				.as_array()
+				.into_iter()
+				.flatten()
+				.filter_map(|v| v.as_str())
+				.collect();
+			println!("{: <40}{}", repo, tags.join(", "));
+		}
+		Ok(())
+	}
+
+	/// Returns true if the local registry of this realm answers
+	pub fn registry_is_reachable(&self, realm: Option<&Realm>) -> bool {
+		registry_get(&self.local_registry(realm), "/v2/").is_ok()
+	}
+
+	/// When the local registry is not reachable, ask to start it (return true if reachable after)
+	pub fn registry_offer_start(&self, realm: Option<&Realm>) -> Result<bool, KddError> {
+		let registry = self.local_registry(realm);
+		if is_dry_run() {
+			println!("(dry-run) local registry {} not reachable (start it with `kdd registry start`)", registry);
+			return Ok(true);
+		}
+
+		println!("Local registry {} is not reachable. Start it (kdd registry start)? (Y/n)", registry);
+		let mut answer = String::new();
+		// Note: no input (e.g., CI without stdin) is a no
+		if stdin().read_line(&mut answer)? == 0 || answer.trim().eq_ignore_ascii_case("n") {
+			return Ok(false);
+		}
+
+		self.registry_start(realm)?;
+		// give some time to the registry to listen
+		for _ in 0..10 {
+			if self.registry_is_reachable(realm) {
+				return Ok(true);
+			}
+			sleep(Duration::from_millis(500));
+		}
+		Ok(false)
+	}
+
+	/// Returns the local registry container status (e.g., `running`, `exited`), None if no container
+	fn registry_container_status(&self, realm: Option<&Realm>) -> Result<Option<String>, KddError> {
+		let engine = self.engine(realm)?;
+		if !engine.can_run() {
+			return Err(KddError::ContainerEngineCannotRun(engine.cmd().to_string()));
+		}
+		let status = query_to_stdout(None, engine.cmd(), &["inspect", "-f", "{{.State.Status}}", REGISTRY_CONTAINER]).ok();
+		// Note: on failure (e.g., no such container), the output is the error message, so, only a status word
+		let status = status.map(|s| s.trim().to_string());
+		Ok(status.filter(|s| !s.is_empty() && s.chars().all(|c| c.is_ascii_lowercase())))
+	}
+}
+
+// region:    Utils
+const MANIFEST_ACCEPT: &str = "application/vnd.docker.distribution.manifest.v2+json, application/vnd.oci.image.manifest.v1+json, \
+application/vnd.docker.distribution.manifest.list.v2+json, application/vnd.oci.image.index.v1+json";
+
+/// Returns the manifest digest of this image uri (e.g., `localhost:5000/cstar-agent:1.0.0`) from the (plain http) registry api
+pub fn registry_manifest_digest(image_uri: &str) -> Option<String> {
+	let (registry, name) = image_uri.split_once('/')?;
+	let (name, tag) = name.rsplit_once(':')?;
+	let path = format!("/v2/{}/manifests/{}", name, tag);
+	let (head, _) = registry_request(registry, "HEAD", &path, MANIFEST_ACCEPT).ok()?;
+	header_value(&head, "docker-content-digest")
+}
+
+/// Returns the tags of this repository (e.g., `localhost:5000/cstar-agent`), with their digest and creation time
+pub fn registry_image_tags(repo: &str) -> Result<Vec<ImageTag>, KddError> {
+	let (registry, name) = repo.split_once('/').unwrap_or((repo, ""));
+	let tags = registry_get(registry, &format!("/v2/{}/tags/list", name))?;
+
+	let mut image_tags = Vec::new();
+	for tag in tags["tags"].as_array().into_iter().flatten().filter_map(|v| v.as_str()) {
+		// the manifest digest (header), and its config (body) for the creation time
+		let path = format!("/v2/{}/manifests/{}", name, tag);
+		let (head, body) = registry_request(registry, "GET", &path, MANIFEST_ACCEPT)?;
+		let config_digest = serde_json::from_str::<Value>(&body)
+			.ok()
+			.and_then(|manifest| manifest["config"]["digest"].as_str().map(|d| d.to_string()));
+		let created = config_digest.and_then(|digest| {
+			let config = registry_get(registry, &format!("/v2/{}/blobs/{}", name, digest)).ok()?;
+			config["created"].as_str().and_then(parse_timestamp)
+		});
+		image_tags.push(ImageTag {
+			tag: tag.to_string(),
+			created,
+			digest: header_value(&head, "docker-content-digest"),
+		});
+	}
+	Ok(image_tags)
+}
+
+/// Delete this manifest digest of this repository (i.e., all of its tags).
+/// Note: The registry must have the delete enabled (as the `kdd registry start` one)
+pub fn registry_delete_manifest(repo: &str, digest: &str) -> Result<(), KddError> {
+	let (registry, name) = repo.split_once('/').unwrap_or((repo, ""));
+	if is_dry_run() {
+		println!("(dry-run) > DELETE {}/v2/{}/manifests/{}", registry, name, digest);
+		return Ok(());
+	}
+	registry_request(registry, "DELETE", &format!("/v2/{}/manifests/{}", name, digest), MANIFEST_ACCEPT)?;
+	Ok(())
+}
+
+/// A minimal registry v2 api GET (plain http, as for a local registry), returning the json body
+fn registry_get(registry: &str, path: &str) -> Result<Value, KddError> {
+	let (_, body) = registry_request(registry, "GET", path, "application/json")?;
+	if body.trim().is_empty() {
+		return Ok(Value::Null);
+	}
+	serde_json::from_str(&body).map_err(|ex| KddError::RegistryRequestFailed(format!("{}{}", registry, path), ex.to_string()))
+}
+
+/// A minimal registry v2 api request (plain http), returning the response head and body (error if not 2xx)
+fn registry_request(registry: &str, method: &str, path: &str, accept: &str) -> Result<(String, String), KddError> {
+	let fail = |cause: String| KddError::RegistryRequestFailed(format!("{}{}", registry, path), cause);
+
+	let addr = registry
+		.to_socket_addrs()
+		.map_err(|ex| fail(ex.to_string()))?
+		.next()
+		.ok_or_else(|| fail("no address".to_string()))?;
+	let mut stream = TcpStream::connect_timeout(&addr, HTTP_TIMEOUT).map_err(|ex| fail(ex.to_string()))?;
+	stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+
+	// Note: HTTP/1.0 to get the body without chunked encoding
+	write!(stream, "{} {} HTTP/1.0\r\nHost: {}\r\nAccept: {}\r\n\r\n", method, path, registry, accept)?;
+	let mut response = String::new();
+	stream.read_to_string(&mut response)?;
+
+	let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+	let status = head.lines().next().unwrap_or_default();
+	if !status
+		.split_whitespace()
+		.nth(1)
+		.map(|code| code.starts_with('2'))
+		.unwrap_or(false)
+	{
+		return Err(fail(status.to_string()));
+	}
+	Ok((head.to_string(), body.to_string()))
+}
+
+fn header_value(head: &str, name: &str) -> Option<String> {
+	head.lines().find_map(|line| {
+		let (line_name, val) = line.split_once(':')?;
+		line_name.trim().eq_ignore_ascii_case(name).then(|| val.trim().to_string())
+	})
+}
+// endregion: Utils
@@ -0,0 +1,213 @@
+////////////////////////////////////
+// kdd::report - The build/dbuild/dpush summary and its machine readable (json) version
+////
+
+use super::error::KddError;
+use serde_json::{json, Value};
+use std::{
+	fs::write,
+	path::Path,
+	time::{Duration, Instant},
+};
+
+/// The report of one build/dbuild/dpush command (filled as the blocks get processed)
+#[derive(Debug)]
+pub struct BuildReport {
+	command: String,
+	realm: Option<String>,
+	start: Instant,
+	blocks: Vec<BlockReport>,
+}
+
+#[derive(Debug)]
+pub struct BlockReport {
+	pub name: String,
+	pub builders: Vec<StepReport>,
+	pub image: Option<StepReport>,
+	pub push: Option<StepReport>,
+}
+
+/// One executed (or skipped) step. `name` is the builder name or the image uri for the docker steps
+#[derive(Debug)]
+pub struct StepReport {
+	pub name: String,
+	pub status: StepStatus,
+	pub duration: Duration,
+	/// docker image id (for the docker build) or repo digest (for the push)
+	pub digest: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum StepStatus {
+	Ok,
+	Skipped(String),
+	Failed(String),
+}
+
+//// BuildReport Builder(s)
+impl BuildReport {
+	pub fn new(command: &str, realm: Option<&str>) -> BuildReport {
+		BuildReport {
+			command: command.to_string(),
+			realm: realm.map(|r| r.to_string()),
+			start: Instant::now(),
+			blocks: Vec::new(),
+		}
+	}
+}
+
+//// BuildReport Public Methods
+impl BuildReport {
+	/// Returns the report for this block (created on first access, so that the blocks stay in build order)
+	pub fn block(&mut self, name: &str) -> &mut BlockReport {
+		let idx = match self.blocks.iter().position(|b| b.name == name) {
+			Some(idx) => idx,
+			None => {
+				self.blocks.push(BlockReport {
+					name: name.to_string(),
+					builders: Vec::new(),
+					image: None,
+					push: None,
+				});
+				self.blocks.len() - 1
+			}
+		};
+		&mut self.blocks[idx]
+	}
+
+	pub fn is_success(&self) -> bool {
+		self.blocks
+			.iter()
+			.all(|b| b.steps().all(|s| !matches!(s.status, StepStatus::Failed(_))))
+	}
+
+	pub fn print_summary(&self) {
+		if self.blocks.is_empty() {
+			return;
+		}
+		println!("==================   Summary ({}) ==================", self.command);
+		tr_print("BLOCK", "BUILDERS", "IMAGE", "PUSH", "TIME");
+		for block in self.blocks.iter() {
+			let builders: Vec<String> = block
+				.builders
+				.iter()
+				.map(|b| format!("{} {}", b.name, b.status_and_time()))
+				.collect();
+			let builders = if builders.is_empty() { "-".to_string() } else { builders.join(", ") };
+			let image = block.image.as_ref().map(|s| format!("{} {}", s.name, s.status_and_time()));
+			let push = block.push.as_ref().map(|s| s.status_and_time());
+			let time = fmt_duration(block.steps().map(|s| s.duration).sum());
+			tr_print(
+				&block.name,
+				&builders,
+				image.as_deref().unwrap_or("-"),
+				push.as_deref().unwrap_or("-"),
+				&time,
+			);
+		}
+		println!(
+			"==================  /Summary - {} in {} ==================\n",
+			if self.is_success() { "OK" } else { "FAILED" },
+			fmt_duration(self.start.elapsed())
+		);
+	}
+
+	pub fn to_json(&self) -> Value {
+		let blocks: Vec<Value> = self
+			.blocks
+			.iter()
+			.map(|block| {
+				json!({
+					"name": block.name,
+					"builders": block.builders.iter().map(|b| b.to_json("name")).collect::<Vec<Value>>(),
+					"image": block.image.as_ref().map(|s| s.to_json("uri")),
+					"push": block.push.as_ref().map(|s| s.to_json("uri")),
+					"duration_ms": block.steps().map(|s| s.duration).sum::<Duration>().as_millis() as u64,
+				})
+			})
+			.collect();
+
+		json!({
+			"command": self.command,
+			"realm": self.realm,
+			"success": self.is_success(),
+			"duration_ms": self.start.elapsed().as_millis() as u64,
+			"blocks": blocks,
+		})
+	}
+
+	pub fn write_json(&self, path: &Path) -> Result<(), KddError> {
+		let content = serde_json::to_string_pretty(&self.to_json())?;
+		write(path, content)?;
+		println!("Build report written to {}", path.to_string_lossy());
+		Ok(())
+	}
+}
+
+impl BlockReport {
+	fn steps(&self) -> impl Iterator<Item = &StepReport> {
+		self.builders.iter().chain(self.image.iter()).chain(self.push.iter())
+	}
+}
+
+//// StepReport Builder(s)
+impl StepReport {
+	/// Create the report of a step from its result, and the instant it started
+	pub fn from_result<T>(name: &str, start: Instant, res: &Result<T, KddError>) -> StepReport {
+		let status = match res {
+			Ok(_) => StepStatus::Ok,
+			Err(ex) => StepStatus::Failed(ex.to_string()),
+		};
+		StepReport {
+			name: name.to_string(),
+			status,
+			duration: start.elapsed(),
+			digest: None,
+		}
+	}
+
+	pub fn skipped(name: &str, reason: &str) -> StepReport {
+		StepReport {
+			name: name.to_string(),
+			status: StepStatus::Skipped(reason.to_string()),
+			duration: Duration::ZERO,
+			digest: None,
+		}
+	}
+}
+
+impl StepReport {
+	fn status_and_time(&self) -> String {
+		match &self.status {
+			StepStatus::Ok => format!("ok {}", fmt_duration(self.duration)),
+			StepStatus::Skipped(_) => "skipped".to_string(),
+			StepStatus::Failed(_) => format!("FAILED {}", fmt_duration(self.duration)),
+		}
+	}
+
+	fn to_json(&self, name_prop: &str) -> Value {
+		let (status, reason) = match &self.status {
+			StepStatus::Ok => ("ok", None),
+			StepStatus::Skipped(reason) => ("skipped", Some(reason)),
+			StepStatus::Failed(cause) => ("failed", Some(cause)),
+		};
+		let mut value = json!({
+			"status": status,
+			"reason": reason,
+			"digest": self.digest,
+			"duration_ms": self.duration.as_millis() as u64,
+		});
+		value[name_prop] = json!(self.name);
+		value
+	}
+}
+
+// region:    Utils
+fn fmt_duration(duration: Duration) -> String {
+	format!("{:.1}s", duration.as_secs_f64())
+}
+
+fn tr_print(block: &str, builders: &str, image: &str, push: &str, time: &str) {
+	println!("{: <16}{: <48}{: <60}{: <14}{}", block, builders, image, push, time);
+}
+// endregion: Utils
@@ -0,0 +1,153 @@
+////////////////////////////////////
+// kdd::run_policy - The timeout and retries policy for the builders and docker steps
+////
+
+use super::{error::KddError, proc_log::ProcLog};
+use crate::utils::yamls::{as_str, to_string};
+use crate::utils::UtilsError;
+use std::future::Future;
+use std::thread;
+use std::time::Duration;
+use tokio::time::sleep;
+use yaml_rust::Yaml;
+
+const POLICY_KEY_TIMEOUT: &str = "timeout";
+const POLICY_KEY_RETRIES: &str = "retries";
+
+// first retry backoff, doubled at each retry (up to the max)
+const RETRY_BACKOFF_FIRST: Duration = Duration::from_secs(2);
+const RETRY_BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+/// Timeout (process tree killed once exceeded) and retries (with exponential backoff) for a step.
+#[derive(Debug, Clone, Default)]
+pub struct RunPolicy {
+	pub timeout: Option<Duration>,
+	pub retries: u32,
+}
+
+//// RunPolicy Builder(s)
+impl RunPolicy {
+	/// Parse the `timeout` (e.g., `300`, `"30s"`, `"5m"`, `"1h"`) and `retries` properties of this yaml
+	pub fn from_yaml(yaml: &Yaml) -> Result<RunPolicy, KddError> {
+		let timeout = match to_string(&yaml[POLICY_KEY_TIMEOUT]) {
+			// Note: a zero timeout would kill the step right away
+			Some(timeout) => match parse_duration(&timeout).filter(|t| !t.is_zero()) {
+				Some(timeout) => Some(timeout),
+				None => return Err(KddError::InvalidRunPolicy(POLICY_KEY_TIMEOUT.to_string(), timeout)),
+			},
+			None => None,
+		};
+
+		let retries = match &yaml[POLICY_KEY_RETRIES] {
+			Yaml::Integer(retries) if *retries >= 0 => *retries as u32,
+			Yaml::BadValue | Yaml::Null => 0,
+			other => {
+				let val = as_str(yaml, POLICY_KEY_RETRIES).map(|v| v.to_string()).unwrap_or_else(|| format!("{:?}", other));
+				return Err(KddError::InvalidRunPolicy(POLICY_KEY_RETRIES.to_string(), val));
+			}
+		};
+
+		Ok(RunPolicy { timeout, retries })
+	}
+}
+
+//// RunPolicy Public Methods
+impl RunPolicy {
+	/// Run the async step, and retry it (with backoff) as per this policy
+	pub async fn run_async<F, Fut>(&self, step: &str, log: &ProcLog, f: F) -> Result<(), KddError>
+	where
+		F: Fn() -> Fut,
+		Fut: Future<Output = Result<(), KddError>>,
+	{
+		let mut attempt = 0;
+		loop {
+			match f().await {
+				Ok(_) => return Ok(()),
+				Err(ex) => sleep(self.next_backoff(step, log, attempt, ex)?).await,
+			}
+			attempt += 1;
+		}
+	}
+
+	/// Run the blocking step, and retry it (with backoff) as per this policy
+	pub fn run<F>(&self, step: &str, log: &ProcLog, f: F) -> Result<(), KddError>
+	where
+		F: Fn() -> Result<(), KddError>,
+	{
+		let mut attempt = 0;
+		loop {
+			match f() {
+				Ok(_) => return Ok(()),
+				Err(ex) => thread::sleep(self.next_backoff(step, log, attempt, ex)?),
+			}
+			attempt += 1;
+		}
+	}
+
+	/// Returns the backoff before the next attempt, or the final error if no more retries
+	fn next_backoff(&self, step: &str, log: &ProcLog, attempt: u32, ex: KddError) -> Result<Duration, KddError> {
+		if attempt >= self.retries {
+			return if self.retries == 0 {
+				Err(ex)
+			} else {
+				Err(KddError::RetriesExhausted(step.to_string(), self.retries + 1, ex.to_string()))
+			};
+		}
+
+		let backoff = retry_backoff(attempt);
+		log.line(&format!(
+			"KDD WARNING - '{}' failed (attempt {}/{}). Cause: {}. Retrying in {}s",
+			step,
+			attempt + 1,
+			self.retries + 1,
+			ex,
+			backoff.as_secs()
+		));
+		Ok(backoff)
+	}
+}
+
+// region:    Utils
+/// The backoff before the retry of this attempt (starting at 0), doubled at each attempt (up to the max)
+pub fn retry_backoff(attempt: u32) -> Duration {
+	RETRY_BACKOFF_FIRST
+		.saturating_mul(2u32.saturating_pow(attempt))
+		.min(RETRY_BACKOFF_MAX)
+}
+
+/// Returns the timeout and retries exhausted errors as they are (distinct error kinds), and the others wrapped by `wrap`
+/// (e.g., `KddError::DpushFailed`)
+pub fn policy_error<F>(ex: KddError, wrap: F) -> KddError
+where
+	F: FnOnce(String) -> KddError,
+{
+	match ex {
+		KddError::ExecTimeout(..) | KddError::RetriesExhausted(..) => ex,
+		KddError::UtilsError(UtilsError::ExecTimeout(cmd, secs)) => KddError::ExecTimeout(cmd, secs),
+		ex => wrap(ex.to_string()),
+	}
+}
+
+/// Parse a duration like `300` (seconds), `"300ms"`, `"300s"`, `"5m"`, `"1h"` or `"30d"`
+pub fn parse_duration(val: &str) -> Option<Duration> {
+	let val = val.trim();
+	if let Some(num) = val.strip_suffix("ms") {
+		return num.trim().parse::<u64>().ok().map(Duration::from_millis);
+	}
+	let (num, unit_secs) = match val.char_indices().last()? {
+		(idx, 's') => (&val[..idx], 1),
+		(idx, 'm') => (&val[..idx], 60),
+		(idx, 'h') => (&val[..idx], 3600),
+		(idx, 'd') => (&val[..idx], 86400),
+		_ => (val, 1),
+	};
+	// Note: None on overflow (rather than a wrapped, short, duration)
+	num.trim().parse::<u64>().ok()?.checked_mul(unit_secs).map(Duration::from_secs)
+}
+// endregion: Utils
+
+// region:    Tests
+#[cfg(test)]
+#[path = "../_test/kdd_run_policy.rs"]
+mod tests;
+// endregion: Tests
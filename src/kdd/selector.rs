@@ -0,0 +1,172 @@
+////////////////////////////////////
+// kdd::selector - The selection of blocks and k8s files by names, `@tag`, globs (`web*`), and `!` exclusions
+////
+
+use super::{error::KddError, realm::Realm, Kdd};
+use regex::Regex;
+
+// max number of similar names suggested for an unmatched selector
+const SUGGESTIONS_MAX: usize = 3;
+
+/// A named item which can be selected (e.g., a block, a k8s file), with its tags
+pub struct Selectable<'a> {
+	pub name: &'a str,
+	pub tags: &'a [String],
+}
+
+/// Returns the names of the items matching these selectors, e.g., `web-server`, `@backend`, `web*`, `!admin`.
+/// Names are returned in the selectors order (items order for the tags and globs), and without the excluded ones.
+/// Exclusions only (e.g., `!admin`) select all of the other items.
+/// A selector matching nothing is an error (with the similar names, if any).
+pub fn select_names<'a>(kind: &str, items: &[Selectable<'a>], selectors: &[&str]) -> Result<Vec<&'a str>, KddError> {
+	let selectors: Vec<&str> = selectors.iter().map(|s| s.trim()).filter(|s| !s.is_empty()).collect();
+	let (excludes, includes): (Vec<&str>, Vec<&str>) = selectors.iter().partition(|s| s.starts_with('!'));
+
+	let mut names: Vec<&str> = Vec::new();
+	if includes.is_empty() {
+		names.extend(items.iter().map(|item| item.name));
+	}
+	for selector in includes {
+		let matched = matching_names(kind, items, selector)?;
+		for name in matched {
+			if !names.contains(&name) {
+				names.push(name);
+			}
+		}
+	}
+
+	for selector in excludes {
+		let excluded = matching_names(kind, items, &selector[1..])?;
+		names.retain(|name| !excluded.contains(name));
+	}
+
+	Ok(names)
+}
+
+//// Kdd Selector Methods
+impl Kdd {
+	/// Returns the names of the realm k8s files matching these selectors (`@tag` matches the files named as the tagged blocks)
+	pub fn select_k8s_names(&self, realm: &Realm, selectors: &[&str]) -> Result<Vec<String>, KddError> {
+		let k8s_names = realm.k8s_names();
+		let items: Vec<Selectable> = k8s_names
+			.iter()
+			.map(|name| Selectable {
+				name,
+				tags: self
+					.blocks
+					.iter()
+					.find(|b| &b.name == name)
+					.and_then(|b| b.tags.as_deref())
+					.unwrap_or(&[]),
+			})
+			.collect();
+
+		let names = select_names("k8s file", &items, selectors)?;
+		Ok(names.into_iter().map(|name| name.to_string()).collect())
+	}
+}
+
+// region:    Utils
+/// Returns the names matching this selector (without `!`), or an error if none
+fn matching_names<'a>(kind: &str, items: &[Selectable<'a>], selector: &str) -> Result<Vec<&'a str>, KddError> {
+	let names: Vec<&str> = if let Some(tag) = selector.strip_prefix('@') {
+		items
+			.iter()
+			.filter(|item| item.tags.iter().any(|t| t == tag))
+			.map(|item| item.name)
+			.collect()
+	} else if selector.contains(['*', '?']) {
+		match name_glob_to_regex(selector) {
+			Some(rx) => items
+				.iter()
+				.filter(|item| rx.is_match(item.name))
+				.map(|item| item.name)
+				.collect(),
+			None => Vec::new(),
+		}
+	} else {
+		items
+			.iter()
+			.filter(|item| item.name == selector)
+			.map(|item| item.name)
+			.collect()
+	};
+
+	if names.is_empty() {
+		Err(KddError::SelectorNoMatch(
+			kind.to_string(),
+			selector.to_string(),
+			suggestions(items, selector),
+		))
+	} else {
+		Ok(names)
+	}
+}
+
+/// Convert a name glob (`*` and `?`) to its full match regex
+fn name_glob_to_regex(glob: &str) -> Option<Regex> {
+	let rx: String = glob
+		.chars()
+		.map(|c| match c {
+			'*' => ".*".to_string(),
+			'?' => ".".to_string(),
+			c => regex::escape(&c.to_string()),
+		})
+		.collect();
+	Regex::new(&format!("^{}$", rx)).ok()
+}
+
+/// Returns the ", did you mean ...?" text with the names (or `@tags`) similar to this selector (empty if none)
+fn suggestions(items: &[Selectable], selector: &str) -> String {
+	let candidates: Vec<String> = match selector.strip_prefix('@') {
+		Some(_) => {
+			let mut tags: Vec<String> = items
+				.iter()
+				.flat_map(|item| item.tags.iter().map(|t| format!("@{}", t)))
+				.collect();
+			tags.sort();
+			tags.dedup();
+			tags
+		}
+		None => items.iter().map(|item| item.name.to_string()).collect(),
+	};
+
+	// similar when close enough, or containing the selector (without its wildcards)
+	let max_distance = (selector.len() / 3).max(2);
+	let part = selector.trim_matches(['@', '*', '?']);
+	let mut similar: Vec<(usize, String)> = candidates
+		.into_iter()
+		.map(|c| (levenshtein(selector, &c), c))
+		.filter(|(distance, c)| *distance <= max_distance || (!part.is_empty() && c.contains(part)))
+		.collect();
+	similar.sort();
+
+	let similar: Vec<String> = similar.into_iter().take(SUGGESTIONS_MAX).map(|(_, c)| c).collect();
+	if similar.is_empty() {
+		String::new()
+	} else {
+		format!(", did you mean {}?", similar.join(", "))
+	}
+}
+
+/// The edit distance between two strings (insertions, deletions, substitutions)
+fn levenshtein(a: &str, b: &str) -> usize {
+	let b: Vec<char> = b.chars().collect();
+	let mut prev: Vec<usize> = (0..=b.len()).collect();
+	for (i, ca) in a.chars().enumerate() {
+		let mut row = vec![i + 1];
+		for (j, cb) in b.iter().enumerate() {
+			let cost = if ca == *cb { 0 } else { 1 };
+			row.push((prev[j] + cost).min(prev[j + 1] + 1).min(row[j] + 1));
+		}
+		prev = row;
+	}
+	prev[b.len()]
+}
+// endregion: Utils
+
+// region:    Tests
+#[cfg(test)]
+#[path = "../_test/kdd_selector.rs"]
+mod tests;
+// endregion: Tests
@@ -0,0 +1,170 @@
+////////////////////////////////////
+// kdd::task - The user defined tasks (kdd.yaml `tasks:`) and their execution (`kdd run <task>`)
+////
+
+use super::{builder::Exec, error::KddError, proc_log::ProcLog, Kdd};
+use crate::utils::path_to_string;
+use crate::utils::yamls::{as_string, as_strings};
+use handlebars::Handlebars;
+use yaml_rust::Yaml;
+
+// the log "block" name for the tasks without block scope
+const TASK_LOG_BLOCK: &str = "task";
+
+//// Task Struct
+#[derive(Debug)]
+pub struct Task {
+	pub name: String,
+	pub description: Option<String>,
+	/// When set, the task exec runs in the context of this block (block dir, block vars)
+	pub block: Option<String>,
+	/// Tasks to run before this one (each task run once per `kdd run`)
+	pub dependencies: Option<Vec<String>>,
+	pub exec: Exec,
+}
+
+//// Task Builder(s)
+impl Task {
+	pub fn from_yaml(yaml: &Yaml) -> Option<Task> {
+		let name = yaml["name"].as_str()?;
+
+		let exec = match Exec::from_yaml(&yaml["exec"]) {
+			Ok(exec) => exec,
+			Err(ex) => {
+				eprintln!(
+					"KDD PARSING WARNING - Task {} does not have a valid exec element. Cause: {}. Skipping",
+					name, ex
+				);
+				return None;
+			}
+		};
+
+		Some(Task {
+			name: name.to_string(),
+			description: as_string(yaml, "description"),
+			block: as_string(yaml, "block"),
+			dependencies: as_strings(yaml, "dependencies"),
+			exec,
+		})
+	}
+}
+
+//// Kdd Task Methods
+impl Kdd {
+	pub fn print_tasks(&self) {
+		if self.tasks.is_empty() {
+			println!("No tasks defined (see the kdd.yaml `tasks:` section)");
+			return;
+		}
+		tr_print("TASK", "BLOCK", "DEPENDENCIES", "DESCRIPTION");
+		for task in self.tasks.iter() {
+			let deps = task
+				.dependencies
+				.as_ref()
+				.map(|v| v.join(","))
+				.unwrap_or_else(|| "-".to_string());
+			tr_print(
+				&task.name,
+				task.block.as_deref().unwrap_or("-"),
+				&deps,
+				task.description.as_deref().unwrap_or(""),
+			);
+		}
+	}
+
+	/// Run the task (after its dependencies). The extra args are appended to this task exec args only.
+	#[tokio::main(flavor = "current_thread")]
+	pub async fn run_task(&self, name: &str, extra_args: &[String]) -> Result<(), KddError> {
+		let tasks = tasks_in_order(&self.tasks, name)?;
+		let realm = self.current_realm().ok().flatten();
+		let hbs = Handlebars::new();
+
+		for task in tasks {
+			// -- the block context (block dir and block vars)
+			let block = match &task.block {
+				Some(block_name) => Some(
+					self.blocks
+						.iter()
+						.find(|b| &b.name == block_name)
+						.ok_or_else(|| KddError::BlockUnknown(block_name.to_string()))?,
+				),
+				None => None,
+			};
+			let block_dir = block.map(|b| self.get_block_dir(b)).unwrap_or_else(|| self.dir.clone());
+
+			// -- the vars (for the exec rendering) and env
+			let mut vars = self.merged_vars(realm);
+			let mut env: Vec<(String, String)> = vec![
+				("KDD_SYSTEM".to_string(), self.system.to_string()),
+				("KDD_DIR".to_string(), path_to_string(&self.dir)?),
+				("KDD_TASK".to_string(), task.name.to_string()),
+			];
+			if let Some(realm) = realm {
+				vars.insert("realm".to_string(), realm.name.to_string());
+				env.push(("KDD_REALM".to_string(), realm.name.to_string()));
+			}
+			if let Some(block) = block {
+				vars.insert("block".to_string(), block.name.to_string());
+				vars.insert("block_dir".to_string(), path_to_string(&block_dir)?);
+				env.push(("KDD_BLOCK".to_string(), block.name.to_string()));
+				env.push(("KDD_BLOCK_DIR".to_string(), path_to_string(&block_dir)?));
+			}
+
+			let mut exec = task.exec.render(&hbs, &vars)?.with_env(&env);
+			if task.name == name {
+				exec = exec.with_extra_args(extra_args);
+			}
+
+			println!("======  Task '{}' ", task.name);
+			let log = ProcLog::new(self, block.map(|b| b.name.as_str()).unwrap_or(TASK_LOG_BLOCK), &task.name)?;
+			exec.execute_and_wait(&self.dir, &block_dir, false, &log, None).await?;
+			println!("====== /Task '{}' DONE\n", task.name);
+		}
+
+		Ok(())
+	}
+}
+
+// region:    Tasks Ordering
+/// Returns the task with its dependencies (recursively) in their execution order (each task once)
+pub fn tasks_in_order<'a>(tasks: &'a [Task], name: &str) -> Result<Vec<&'a Task>, KddError> {
+	fn visit<'a>(all: &'a [Task], name: &str, visiting: &mut Vec<String>, tasks: &mut Vec<&'a Task>) -> Result<(), KddError> {
+		if tasks.iter().any(|t| t.name == name) {
+			return Ok(());
+		}
+		if visiting.iter().any(|n| n == name) {
+			visiting.push(name.to_string());
+			return Err(KddError::TasksCycle(visiting.join(" > ")));
+		}
+		let task = all
+			.iter()
+			.find(|t| t.name == name)
+			.ok_or_else(|| KddError::TaskUnknown(name.to_string()))?;
+
+		visiting.push(name.to_string());
+		for dep_name in task.dependencies.iter().flatten() {
+			visit(all, dep_name, visiting, tasks)?;
+		}
+		visiting.pop();
+
+		tasks.push(task);
+		Ok(())
+	}
+
+	let mut ordered: Vec<&Task> = Vec::new();
+	visit(tasks, name, &mut Vec::new(), &mut ordered)?;
+	Ok(ordered)
+}
+// endregion: Tasks Ordering
+
+// region:    Utils
+fn tr_print(task: &str, block: &str, deps: &str, description: &str) {
+	println!("{: <20}{: <16}{: <28}{}", task, block, deps, description);
+}
+// endregion: Utils
+
+// region:    Tests
+#[cfg(test)]
+#[path = "../_test/kdd_task.rs"]
+mod tests;
+// endregion: Tests
@@ -0,0 +1,289 @@
+////////////////////////////////////
+// kdd::watcher - The kdd file watching (for the builders without their own watch mode)
+////
+
+use super::{error::KddError, proc_log::ProcLog, run_policy::parse_duration, Block, Kdd};
+use crate::utils::yamls::{as_strings, to_string};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tokio::time::{sleep, timeout};
+use yaml_rust::Yaml;
+
+const WATCH_KEY_DEBOUNCE: &str = "debounce";
+const WATCH_KEY_IGNORE: &str = "ignore";
+
+const WATCH_DEBOUNCE_DEFAULT: Duration = Duration::from_millis(300);
+// always ignored (on top of the kdd.yaml `watch.ignore`)
+const WATCH_IGNORE_DEFAULT: &[&str] = &[".git", ".kdd", "node_modules", "target"];
+
+/// The kdd.yaml `watch:` section (debounce and ignore globs, relative to the block dir)
+#[derive(Debug)]
+pub struct WatchConfig {
+	pub debounce: Duration,
+	ignore: Vec<Regex>,
+}
+
+//// WatchConfig Builder(s)
+impl WatchConfig {
+	pub fn from_yaml(yaml: &Yaml) -> Result<WatchConfig, KddError> {
+		let debounce = match to_string(&yaml[WATCH_KEY_DEBOUNCE]) {
+			Some(val) => parse_duration(&val).ok_or(KddError::InvalidWatchConfig(WATCH_KEY_DEBOUNCE.to_string(), val))?,
+			None => WATCH_DEBOUNCE_DEFAULT,
+		};
+
+		let globs = WATCH_IGNORE_DEFAULT
+			.iter()
+			.map(|glob| glob.to_string())
+			.chain(as_strings(yaml, WATCH_KEY_IGNORE).unwrap_or_default());
+		let ignore = globs
+			.map(|glob| glob_to_regex(&glob).ok_or(KddError::InvalidWatchConfig(WATCH_KEY_IGNORE.to_string(), glob)))
+			.collect::<Result<Vec<Regex>, KddError>>()?;
+
+		Ok(WatchConfig { debounce, ignore })
+	}
+}
+
+//// WatchConfig Public Methods
+impl WatchConfig {
+	/// Returns true if this path (relative to the block dir, with `/` separators) matches an ignore glob
+	pub fn is_ignored(&self, rel_path: &str) -> bool {
+		self.ignore.iter().any(|rx| rx.is_match(rel_path))
+	}
+}
+
+/// The watcher of the block dirs, which returns the changed blocks (with their dependent blocks)
+pub struct BlockWatcher<'a> {
+	kdd: &'a Kdd,
+	blocks: Vec<&'a Block>,
+	// the watched dirs (canonicalized, as the event paths)
+	block_dirs: Vec<(&'a Block, PathBuf)>,
+	rx: UnboundedReceiver<PathBuf>,
+	// keep the watcher alive (stops watching when dropped)
+	_watcher: RecommendedWatcher,
+}
+
+//// BlockWatcher Builder(s)
+impl<'a> BlockWatcher<'a> {
+	pub fn new(kdd: &'a Kdd, blocks: &[&'a Block]) -> Result<BlockWatcher<'a>, KddError> {
+		let mut block_dirs: Vec<(&Block, PathBuf)> = Vec::new();
+		for block in blocks.iter() {
+			let dir = kdd.get_block_dir(block);
+			match dir.canonicalize() {
+				Ok(dir) => block_dirs.push((block, dir)),
+				Err(_) => println!("KDD WARNING - block dir {} not found, not watched", dir.to_string_lossy()),
+			}
+		}
+
+		let (tx, rx) = unbounded_channel::<PathBuf>();
+		let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+			if let Ok(event) = res {
+				if !matches!(event.kind, EventKind::Access(_)) {
+					for path in event.paths {
+						let _ = tx.send(path);
+					}
+				}
+			}
+		})
+		.map_err(|ex| KddError::WatchFailed(ex.to_string()))?;
+		for (_, dir) in block_dirs.iter() {
+			watcher
+				.watch(dir, RecursiveMode::Recursive)
+				.map_err(|ex| KddError::WatchFailed(format!("{} - {}", dir.to_string_lossy(), ex)))?;
+		}
+
+		Ok(BlockWatcher {
+			kdd,
+			blocks: blocks.to_vec(),
+			block_dirs,
+			rx,
+			_watcher: watcher,
+		})
+	}
+}
+
+//// BlockWatcher Public Methods
+impl<'a> BlockWatcher<'a> {
+	/// Wait for the next changes (debounced), and returns the changed blocks names, and the blocks to rebuild
+	/// (the changed blocks followed by the blocks depending on them).
+	pub async fn next_change(&mut self) -> Option<(Vec<&'a str>, Vec<&'a Block>)> {
+		let debounce = self.kdd.watch_config.debounce;
+		loop {
+			// -- collect the changes until quiet for the debounce time
+			let mut paths = vec![self.rx.recv().await?];
+			while let Ok(Some(path)) = timeout(debounce, self.rx.recv()).await {
+				paths.push(path);
+			}
+
+			let mut changed: Vec<&str> = Vec::new();
+			for path in paths.iter() {
+				if let Some(block) = self.block_for(path) {
+					if !changed.contains(&block.name.as_str()) {
+						changed.push(&block.name);
+					}
+				}
+			}
+			if !changed.is_empty() {
+				let affected = with_dependents(&self.blocks, &changed);
+				return Some((changed, affected));
+			}
+		}
+	}
+
+	/// Drop the pending changes (e.g., the files generated by the builders that just ran)
+	pub async fn skip_pending(&mut self) {
+		sleep(self.kdd.watch_config.debounce).await;
+		while self.rx.try_recv().is_ok() {}
+	}
+
+	/// Returns the block of this changed path (most specific block dir), if not ignored
+	fn block_for(&self, path: &Path) -> Option<&'a Block> {
+		let (block, rel_path) = self
+			.block_dirs
+			.iter()
+			.filter_map(|(block, dir)| path.strip_prefix(dir).ok().map(|rel_path| (*block, rel_path)))
+			.min_by_key(|(_, rel_path)| rel_path.components().count())?;
+
+		let rel_path = rel_path.to_string_lossy().replace('\\', "/");
+		if rel_path.is_empty() || self.kdd.watch_config.is_ignored(&rel_path) {
+			None
+		} else {
+			Some(block)
+		}
+	}
+}
+
+//// Kdd Watch Methods
+impl Kdd {
+	/// Watch the block dirs, and on change (debounced), re-run the builders without `watch_args` of the changed blocks,
+	/// and then of the blocks depending on them. Does not return (until the process is stopped).
+	pub async fn watch_blocks(&self, blocks: &[&Block]) -> Result<(), KddError> {
+		let mut watcher = BlockWatcher::new(self, blocks)?;
+
+		// -- first run
+		self.watch_run_builders(blocks).await;
+
+		while let Some((changed, affected)) = watcher.next_change().await {
+			let affected_names: Vec<&str> = affected.iter().map(|b| b.name.as_str()).collect();
+			println!(
+				"======  Change in [{}] - running builders for [{}]",
+				changed.join(", "),
+				affected_names.join(", ")
+			);
+			self.watch_run_builders(&affected).await;
+			println!("====== /Change in [{}] DONE - watching...\n", changed.join(", "));
+
+			// drop the changes made by the builders themselves (e.g., generated files)
+			watcher.skip_pending().await;
+		}
+
+		Ok(())
+	}
+
+	/// Run the builders without `watch_args` of these blocks (failures are printed, and do not stop the watch)
+	async fn watch_run_builders(&self, blocks: &[&Block]) {
+		for block in blocks.iter() {
+			let builders = match self.builders_for_block(block) {
+				Ok(builders) => builders,
+				Err(ex) => {
+					println!("KDD WARNING - cannot get builders for [{}]. Cause: {}", block.name, ex);
+					continue;
+				}
+			};
+			let block_dir = self.get_block_dir(block);
+			for builder in builders.into_iter().filter(|b| !b.exec.has_watch_args()) {
+				println!("--- builder - {} for [{}]", builder.name, block.name);
+				let log = match ProcLog::new(self, &block.name, &builder.name) {
+					Ok(log) => log,
+					Err(ex) => {
+						println!("KDD WARNING - cannot create log for [{}:{}]. Cause: {}", block.name, builder.name, ex);
+						continue;
+					}
+				};
+				let policy = &builder.policy;
+				let res = policy
+					.run_async(&builder.name, &log, || {
+						builder
+							.exec
+							.execute_and_wait(&self.dir, &block_dir, false, &log, policy.timeout)
+					})
+					.await;
+				if let Err(ex) = res {
+					println!("KDD WARNING - builder '{}' for [{}] failed. Cause: {}", builder.name, block.name, ex);
+				}
+			}
+		}
+	}
+}
+
+// region:    Utils
+/// Returns the changed blocks, followed by the blocks depending on them (transitively), in dependency order
+fn with_dependents<'a>(blocks: &[&'a Block], changed: &[&str]) -> Vec<&'a Block> {
+	let mut names: Vec<&str> = blocks
+		.iter()
+		.map(|b| b.name.as_str())
+		.filter(|name| changed.contains(name))
+		.collect();
+
+	let mut added = true;
+	while added {
+		added = false;
+		for block in blocks.iter() {
+			if !names.contains(&block.name.as_str()) && block.dependencies.iter().flatten().any(|d| names.contains(&d.as_str())) {
+				names.push(&block.name);
+				added = true;
+			}
+		}
+	}
+
+	names
+		.iter()
+		.filter_map(|name| blocks.iter().find(|b| &b.name == name).copied())
+		.collect()
+}
+
+/// Convert an ignore glob to its regex. As for .gitignore, the glob can match at any depth, and a matching dir
+/// ignores everything below it (e.g., `node_modules`, `dist/**`, `*.log`, `src/generated/*.ts`)
+pub fn glob_to_regex(glob: &str) -> Option<Regex> {
+	let glob = glob.trim().trim_start_matches("./").trim_end_matches('/');
+	if glob.is_empty() {
+		return None;
+	}
+	// a glob starting with `/` is anchored to the block dir
+	let (anchored, glob) = match glob.strip_prefix('/') {
+		Some(glob) => (true, glob),
+		None => (false, glob),
+	};
+
+	let mut rx = String::from(if anchored { "^" } else { "^(.*/)?" });
+	let mut chars = glob.chars().peekable();
+	while let Some(c) = chars.next() {
+		match c {
+			'*' if chars.peek() == Some(&'*') => {
+				chars.next();
+				// `**/` matches zero or more dirs
+				if chars.peek() == Some(&'/') {
+					chars.next();
+					rx.push_str("(.*/)?");
+				} else {
+					rx.push_str(".*");
+				}
+			}
+			'*' => rx.push_str("[^/]*"),
+			'?' => rx.push_str("[^/]"),
+			c => rx.push_str(&regex::escape(&c.to_string())),
+		}
+	}
+	rx.push_str("(/.*)?$");
+
+	Regex::new(&rx).ok()
+}
+// endregion: Utils
+
+// region:    Tests
+#[cfg(test)]
+#[path = "../_test/kdd_watcher.rs"]
+mod tests;
+// endregion: Tests
@@ -13,7 +13,8 @@ use crate::cmd::cmd_run;
 
 fn main() {
 	match cmd_run() {
-		Ok(_) => println!("✔ All good and well"),
+		// Note: on stderr, to not mix with the machine readable outputs (e.g., `kdd blocks --json`)
+		Ok(_) => eprintln!("✔ All good and well"),
 		Err(e) => {
 			println!("Error:\n  {}", e)
 		}
@@ -1,10 +1,15 @@
 pub mod jsons;
+pub mod runner;
+pub mod supervisor;
 pub mod yamls;
 
+use runner::ExtCmd;
 use std::{
 	io::Error as IOError,
 	path::PathBuf,
-	process::{Command, ExitStatus, Stdio},
+	process::{Child, Command, ExitStatus},
+	thread,
+	time::{Duration, Instant},
 };
 use thiserror::Error;
 use yaml_rust::Yaml;
@@ -14,21 +19,13 @@ pub enum UtilsError {
 	#[error("Fail to execute {0} cause: {1}")]
 	ExecError(String, String),
 
+	#[error("Fail to execute {0} cause: timeout of {1}s exceeded (process killed)")]
+	ExecTimeout(String, u64),
+
 	#[error("Path '{0}' (lossy representation) seems to not be utf8")]
 	PathNotUtf8(String),
 }
 
-impl UtilsError {
-	fn from_exec_stderr(cmd: &str, args: &[&str], cause: &dyn std::error::Error) -> Self {
-		let command = format!("{} {}", cmd, args.join(" "));
-		UtilsError::ExecError(command, cause.to_string())
-	}
-	fn from_exec_status(cmd: &str, args: &[&str], status: ExitStatus) -> Self {
-		let command = format!("{} {}", cmd, args.join(" "));
-		UtilsError::ExecError(command, status.to_string())
-	}
-}
-
 /// extract the utf8 string of a PathBuf, and throw error if not possible.
 #[inline]
 pub fn path_to_string(path: &PathBuf) -> Result<String, UtilsError> {
@@ -38,56 +35,92 @@ pub fn path_to_string(path: &PathBuf) -> Result<String, UtilsError> {
 	}
 }
 
-pub fn exec_proc(proc: &mut Command) -> Result<ExitStatus, IOError> {
-	Ok(proc.spawn()?.wait()?)
+/// Execute the command (blocking), with the stdio inherited (not executed in dry-run)
+pub fn exec_cmd_args<S: AsRef<str>>(cwd: Option<&PathBuf>, cmd: &str, args: &[S]) -> Result<(), UtilsError> {
+	ExtCmd::new(cmd, args).cwd(cwd.map(|p| p.as_path())).exec()
 }
 
-pub fn exec_cmd_args(cwd: Option<&PathBuf>, cmd: &str, args: &[&str]) -> Result<(), UtilsError> {
-	let mut proc = Command::new(cmd);
-	if let Some(cwd) = cwd {
-		proc.current_dir(cwd);
-	}
-	proc.args(args);
+/// Execute the command (blocking), and call on_line for each of its stdout and stderr lines (not executed in dry-run).
+/// If the timeout is exceeded, the process group of the command gets killed.
+pub fn exec_cmd_args_lines<F>(cwd: Option<&PathBuf>, cmd: &str, args: &[&str], timeout: Option<Duration>, on_line: F) -> Result<(), UtilsError>
+where
+	F: Fn(&str) + Sync,
+{
+	ExtCmd::new(cmd, args)
+		.cwd(cwd.map(|p| p.as_path()))
+		.exec_lines(timeout, on_line)
+}
 
-	println!("> executing: {} {}", cmd, args.join(" "));
+/// Wait for the child, and kill its process group if the timeout is exceeded (returns None in this case)
+pub(crate) fn wait_timeout(child: &mut Child, timeout: Option<Duration>) -> Result<Option<ExitStatus>, IOError> {
+	let timeout = match timeout {
+		Some(timeout) => timeout,
+		None => return child.wait().map(Some),
+	};
 
-	match exec_proc(&mut proc) {
-		Ok(status) => {
-			if !status.success() {
-				Err(UtilsError::from_exec_status(cmd, args, status))
-			} else {
-				Ok(())
-			}
+	let start = Instant::now();
+	loop {
+		if let Some(status) = child.try_wait()? {
+			return Ok(Some(status));
+		}
+		if start.elapsed() >= timeout {
+			kill_process_group(child.id());
+			// reap the killed process
+			child.wait()?;
+			return Ok(None);
 		}
-		Err(ex) => Err(UtilsError::from_exec_stderr(cmd, args, &ex)),
+		thread::sleep(Duration::from_millis(100));
 	}
 }
 
-pub fn exec_to_stdout(cwd: Option<&PathBuf>, cmd: &str, args: &[&str], print_exec: bool) -> Result<String, UtilsError> {
-	if print_exec {
-		println!("> executing: {} {}", cmd, args.join(" "));
+// region:    Process Group Utils
+/// Make the command the leader of a new process group (so that its whole process tree can be killed)
+pub fn set_process_group(proc: &mut Command) {
+	#[cfg(unix)]
+	{
+		use std::os::unix::process::CommandExt;
+		proc.process_group(0);
+	}
+}
+
+/// Kill (SIGKILL) the process group of this process id (which must have been spawned with set_process_group)
+pub fn kill_process_group(pid: u32) {
+	#[cfg(unix)]
+	unsafe {
+		libc::kill(-(pid as i32), libc::SIGKILL);
 	}
-	let mut proc = Command::new(&cmd);
-	if let Some(cwd) = cwd {
-		proc.current_dir(cwd);
+	#[cfg(not(unix))]
+	{
+		let _ = Command::new("taskkill").args(["/F", "/T", "/PID", &pid.to_string()]).status();
 	}
-	proc.args(args);
-	match proc.stdout(Stdio::piped()).output() {
-		Err(ex) => Err(UtilsError::from_exec_stderr(cmd, args, &ex)),
-		Ok(output) => {
-			let txt = if output.status.success() {
-				String::from_utf8(output.stdout)
-			} else {
-				String::from_utf8(output.stderr)
-			};
+}
 
-			match txt {
-				Err(ex) => Err(UtilsError::from_exec_stderr(cmd, args, &ex)),
-				Ok(txt) => Ok(txt),
-			}
-		}
+/// Send this signal to the process group of this process id (kill on non unix)
+pub fn signal_process_group(pid: u32, sig: i32) {
+	#[cfg(unix)]
+	unsafe {
+		libc::kill(-(pid as i32), sig);
+	}
+	#[cfg(not(unix))]
+	{
+		let _ = sig;
+		kill_process_group(pid);
 	}
 }
+// endregion: Process Group Utils
+
+/// Execute the command (blocking) and returns its stdout (not executed in dry-run, returns an empty string)
+pub fn exec_to_stdout(cwd: Option<&PathBuf>, cmd: &str, args: &[&str], print_exec: bool) -> Result<String, UtilsError> {
+	ExtCmd::new(cmd, args).cwd(cwd.map(|p| p.as_path())).exec_to_stdout(print_exec)
+}
+
+/// Execute this read-only query (e.g., `kubectl config current-context`) and returns its stdout (executed even in dry-run)
+pub fn query_to_stdout<S: AsRef<str>>(cwd: Option<&PathBuf>, cmd: &str, args: &[S]) -> Result<String, UtilsError> {
+	ExtCmd::new(cmd, args)
+		.cwd(cwd.map(|p| p.as_path()))
+		.query()
+		.exec_to_stdout(false)
+}
 
 // region:    Yaml Utils
 /// Check if the yaml has a given prop, and if does, just self as
@@ -0,0 +1,207 @@
+////////////////////////////////////
+// utils::runner - The external command runner (docker, kubectl, aws, builders), and the global dry-run mode
+////
+
+use super::supervisor::ChildGuard;
+use super::{set_process_group, wait_timeout, UtilsError};
+use std::{
+	env::current_dir,
+	io::{BufRead, BufReader, Write},
+	path::{Path, PathBuf},
+	process::{Command, Stdio},
+	sync::atomic::{AtomicBool, Ordering},
+	thread,
+	time::Duration,
+};
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// In dry-run mode, the external commands are printed (with their cwd and env), but not executed
+/// (except the read-only queries, see `ExtCmd::query`)
+pub fn set_dry_run(dry_run: bool) {
+	DRY_RUN.store(dry_run, Ordering::SeqCst);
+}
+
+pub fn is_dry_run() -> bool {
+	DRY_RUN.load(Ordering::SeqCst)
+}
+
+/// An external command as it gets executed (cmd, args, cwd, env)
+#[derive(Debug, Clone, Default)]
+pub struct ExtCmd {
+	pub cmd: String,
+	pub args: Vec<String>,
+	pub cwd: Option<PathBuf>,
+	pub env: Vec<(String, String)>,
+	/// A read-only query (e.g., `kubectl config current-context`), executed even in dry-run (to know what would be run)
+	pub query: bool,
+}
+
+//// ExtCmd Builder(s)
+impl ExtCmd {
+	pub fn new<S: AsRef<str>>(cmd: &str, args: &[S]) -> ExtCmd {
+		ExtCmd {
+			cmd: cmd.to_string(),
+			args: args.iter().map(|arg| arg.as_ref().to_string()).collect(),
+			..Default::default()
+		}
+	}
+
+	pub fn cwd(mut self, cwd: Option<&Path>) -> ExtCmd {
+		self.cwd = cwd.map(|cwd| cwd.to_path_buf());
+		self
+	}
+
+	pub fn env(mut self, env: &[(String, String)]) -> ExtCmd {
+		self.env.extend(env.iter().cloned());
+		self
+	}
+
+	pub fn query(mut self) -> ExtCmd {
+		self.query = true;
+		self
+	}
+}
+
+//// ExtCmd Public Methods
+impl ExtCmd {
+	/// Returns the command line (cmd and args, for display)
+	pub fn cmd_line(&self) -> String {
+		format!("{} {}", self.cmd, self.args.join(" "))
+	}
+
+	/// Returns true if this command must not be executed (dry-run), in which case it gets printed with its cwd and env
+	pub fn dry_run(&self) -> bool {
+		if self.query || !is_dry_run() {
+			return false;
+		}
+		let cwd = self.cwd.clone().or_else(|| current_dir().ok()).unwrap_or_default();
+		let cwd = cwd.canonicalize().unwrap_or(cwd);
+		println!("(dry-run) > {}", self.cmd_line());
+		println!("            cwd: {}", cwd.to_string_lossy());
+		for (name, val) in self.env.iter() {
+			println!("            env: {}={}", name, val);
+		}
+		true
+	}
+
+	/// Returns the std process command (cmd, args, cwd, env), for the caller to set the stdio and spawn
+	pub fn command(&self) -> Command {
+		let mut proc = Command::new(&self.cmd);
+		if let Some(cwd) = &self.cwd {
+			proc.current_dir(cwd);
+		}
+		proc.args(&self.args);
+		proc.envs(self.env.iter().map(|(name, val)| (name, val)));
+		proc
+	}
+
+	/// Execute (blocking) with the stdio inherited
+	pub fn exec(&self) -> Result<(), UtilsError> {
+		if self.dry_run() {
+			return Ok(());
+		}
+		println!("> executing: {}", self.cmd_line());
+
+		match self.command().spawn().and_then(|mut child| child.wait()) {
+			Ok(status) if status.success() => Ok(()),
+			Ok(status) => Err(UtilsError::ExecError(self.cmd_line(), status.to_string())),
+			Err(ex) => Err(UtilsError::ExecError(self.cmd_line(), ex.to_string())),
+		}
+	}
+
+	/// Execute (blocking) with this content as stdin (e.g., a password)
+	pub fn exec_with_stdin(&self, stdin: &str) -> Result<(), UtilsError> {
+		if self.dry_run() {
+			return Ok(());
+		}
+		println!("> executing: {} (with stdin)", self.cmd_line());
+
+		let mut proc = self.command();
+		proc.stdin(Stdio::piped());
+		let res = proc.spawn().and_then(|mut child| {
+			if let Some(mut child_stdin) = child.stdin.take() {
+				child_stdin.write_all(stdin.as_bytes())?;
+			}
+			child.wait()
+		});
+		match res {
+			Ok(status) if status.success() => Ok(()),
+			Ok(status) => Err(UtilsError::ExecError(self.cmd_line(), status.to_string())),
+			Err(ex) => Err(UtilsError::ExecError(self.cmd_line(), ex.to_string())),
+		}
+	}
+
+	/// Execute (blocking), and call on_line for each of its stdout and stderr lines.
+	/// If the timeout is exceeded, the process group of the command gets killed.
+	pub fn exec_lines<F>(&self, timeout: Option<Duration>, on_line: F) -> Result<(), UtilsError>
+	where
+		F: Fn(&str) + Sync,
+	{
+		if self.dry_run() {
+			return Ok(());
+		}
+
+		let mut proc = self.command();
+		proc.stdout(Stdio::piped()).stderr(Stdio::piped());
+		set_process_group(&mut proc);
+
+		let mut child = proc
+			.spawn()
+			.map_err(|ex| UtilsError::ExecError(self.cmd_line(), ex.to_string()))?;
+		let guard = ChildGuard::track(child.id());
+		let stdout = child.stdout.take();
+		let stderr = child.stderr.take();
+
+		let status = thread::scope(|s| {
+			let on_line = &on_line;
+			if let Some(stdout) = stdout {
+				s.spawn(move || {
+					BufReader::new(stdout)
+						.lines()
+						.map_while(Result::ok)
+						.for_each(|line| on_line(&line))
+				});
+			}
+			if let Some(stderr) = stderr {
+				s.spawn(move || {
+					BufReader::new(stderr)
+						.lines()
+						.map_while(Result::ok)
+						.for_each(|line| on_line(&line))
+				});
+			}
+			wait_timeout(&mut child, timeout)
+		});
+		guard.done();
+
+		match status {
+			Ok(Some(status)) if status.success() => Ok(()),
+			Ok(Some(status)) => Err(UtilsError::ExecError(self.cmd_line(), status.to_string())),
+			Ok(None) => Err(UtilsError::ExecTimeout(self.cmd_line(), timeout.map(|t| t.as_secs()).unwrap_or_default())),
+			Err(ex) => Err(UtilsError::ExecError(self.cmd_line(), ex.to_string())),
+		}
+	}
+
+	/// Execute (blocking), and returns its stdout (or its stderr if it failed). Returns an empty string in dry-run.
+	pub fn exec_to_stdout(&self, print_exec: bool) -> Result<String, UtilsError> {
+		if self.dry_run() {
+			return Ok(String::new());
+		}
+		if print_exec {
+			println!("> executing: {}", self.cmd_line());
+		}
+
+		match self.command().stdout(Stdio::piped()).output() {
+			Err(ex) => Err(UtilsError::ExecError(self.cmd_line(), ex.to_string())),
+			Ok(output) => {
+				let txt = if output.status.success() {
+					String::from_utf8(output.stdout)
+				} else {
+					String::from_utf8(output.stderr)
+				};
+				txt.map_err(|ex| UtilsError::ExecError(self.cmd_line(), ex.to_string()))
+			}
+		}
+	}
+}
@@ -0,0 +1,194 @@
+////////////////////////////////////
+// utils::supervisor - Track the spawned children (process groups), and stop them on SIGINT/SIGTERM and exit
+////
+
+use super::{kill_process_group, signal_process_group};
+use std::collections::BTreeSet;
+use std::future::pending;
+use std::process::exit;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::time::sleep;
+
+// the time given to the children to stop after the signal was forwarded (before they get killed)
+const STOP_GRACE: Duration = Duration::from_secs(5);
+
+static CHILDREN: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+static STOPPING: AtomicBool = AtomicBool::new(false);
+static STOP_TX: OnceLock<watch::Sender<bool>> = OnceLock::new();
+
+// region:    ChildGuard
+/// A tracked child (spawned with `set_process_group`). Its process group gets killed if still tracked when dropped.
+#[derive(Debug)]
+pub struct ChildGuard {
+	pid: u32,
+}
+
+impl ChildGuard {
+	pub fn track(pid: u32) -> ChildGuard {
+		// Note: pid 0 (e.g., child already reaped) is never tracked, as its group would be the kdd one
+		if pid != 0 {
+			if let Ok(mut children) = CHILDREN.lock() {
+				children.insert(pid);
+			}
+		}
+		ChildGuard { pid }
+	}
+
+	/// The child was waited (exited), so, nothing to kill anymore
+	pub fn done(self) {
+		untrack(self.pid);
+	}
+}
+
+impl Drop for ChildGuard {
+	fn drop(&mut self) {
+		if untrack(self.pid) {
+			kill_process_group(self.pid);
+		}
+	}
+}
+// endregion: ChildGuard
+
+// region:    StopListener
+/// Listener of the first SIGINT/SIGTERM, for the commands stopping on their own (e.g., `kdd dev`).
+/// While subscribed, the signal is forwarded to the children, but kdd does not exit (until a second signal).
+pub struct StopListener {
+	rx: Option<watch::Receiver<bool>>,
+}
+
+impl StopListener {
+	/// Wait until a stop is requested (never returns if the signal handler is not installed)
+	pub async fn wait(&mut self) {
+		match self.rx.as_mut() {
+			Some(rx) => {
+				while !*rx.borrow_and_update() {
+					if rx.changed().await.is_err() {
+						pending::<()>().await;
+					}
+				}
+			}
+			None => pending::<()>().await,
+		}
+	}
+}
+
+pub fn subscribe_stop() -> StopListener {
+	StopListener {
+		rx: STOP_TX.get().map(|tx| tx.subscribe()),
+	}
+}
+// endregion: StopListener
+
+/// Returns true once a SIGINT/SIGTERM was received (e.g., to not restart a stopped child)
+pub fn is_stopping() -> bool {
+	STOPPING.load(Ordering::SeqCst)
+}
+
+/// Kill the process groups of all the tracked children
+pub fn kill_all() {
+	let pids: Vec<u32> = match CHILDREN.lock() {
+		Ok(mut children) => std::mem::take(&mut *children).into_iter().collect(),
+		Err(_) => return,
+	};
+	for pid in pids {
+		kill_process_group(pid);
+	}
+}
+
+/// Kill the remaining tracked children when dropped (i.e., at the end of the command)
+pub struct SupervisorGuard;
+
+impl Drop for SupervisorGuard {
+	fn drop(&mut self) {
+		kill_all();
+	}
+}
+
+/// Install the SIGINT/SIGTERM handler (on its own thread). On signal, it is forwarded to the tracked children,
+/// and kdd exits once they stopped (killed after a grace time, or on a second signal).
+pub fn install_signal_handler() -> SupervisorGuard {
+	let (tx, _) = watch::channel(false);
+	if STOP_TX.set(tx).is_err() {
+		return SupervisorGuard; // already installed
+	}
+
+	thread::spawn(|| {
+		let rt = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+			Ok(rt) => rt,
+			Err(ex) => {
+				println!("KDD WARNING - cannot install the signal handler. Cause: {}", ex);
+				return;
+			}
+		};
+		rt.block_on(async {
+			let sig = next_signal().await;
+			STOPPING.store(true, Ordering::SeqCst);
+			forward_signal(sig);
+
+			// if a command stops on its own (e.g., `kdd dev`), let it, until a second signal
+			if let Some(tx) = STOP_TX.get().filter(|tx| tx.receiver_count() > 0) {
+				println!("\nKDD - stopping (Ctrl-C again to force)...");
+				let _ = tx.send(true);
+				next_signal().await;
+			} else {
+				tokio::select! {
+					_ = children_done(STOP_GRACE) => (),
+					_ = next_signal() => (),
+				}
+			}
+
+			kill_all();
+			exit(128 + sig);
+		});
+	});
+
+	SupervisorGuard
+}
+
+// region:    Utils
+/// Returns true if the pid was still tracked
+fn untrack(pid: u32) -> bool {
+	CHILDREN.lock().map(|mut children| children.remove(&pid)).unwrap_or(false)
+}
+
+fn forward_signal(sig: i32) {
+	if let Ok(children) = CHILDREN.lock() {
+		for pid in children.iter() {
+			signal_process_group(*pid, sig);
+		}
+	}
+}
+
+/// Wait until no more tracked children, or the timeout
+async fn children_done(timeout: Duration) {
+	let start = Instant::now();
+	while start.elapsed() < timeout && CHILDREN.lock().map(|c| !c.is_empty()).unwrap_or(false) {
+		sleep(Duration::from_millis(100)).await;
+	}
+}
+
+/// Returns the next SIGINT or SIGTERM signal number
+#[cfg(unix)]
+async fn next_signal() -> i32 {
+	use tokio::signal::unix::{signal, SignalKind};
+	match (signal(SignalKind::interrupt()), signal(SignalKind::terminate())) {
+		(Ok(mut int), Ok(mut term)) => tokio::select! {
+			_ = int.recv() => libc::SIGINT,
+			_ = term.recv() => libc::SIGTERM,
+		},
+		_ => pending().await,
+	}
+}
+
+#[cfg(not(unix))]
+async fn next_signal() -> i32 {
+	match tokio::signal::ctrl_c().await {
+		Ok(_) => 2, // SIGINT
+		Err(_) => pending().await,
+	}
+}
+// endregion: Utils
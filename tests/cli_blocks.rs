@@ -0,0 +1,37 @@
+use std::error::Error;
+use std::process::Command;
+
+// Note: app-1 has an overlay, which the loader reports (on stderr, not to break the --json and --graph outputs)
+const APP_1_DIR: &str = "./test-data/app-1";
+
+#[test]
+fn cli_blocks_json_stdout() -> Result<(), Box<dyn Error>> {
+	let output = Command::new(env!("CARGO_BIN_EXE_kdd"))
+		.args(["blocks", "--json", "-d", APP_1_DIR])
+		.output()?;
+	assert!(output.status.success());
+
+	let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+	let names: Vec<&str> = json
+		.as_array()
+		.into_iter()
+		.flatten()
+		.filter_map(|b| b["name"].as_str())
+		.collect();
+	assert!(names.contains(&"web-server"));
+
+	Ok(())
+}
+
+#[test]
+fn cli_blocks_graph_stdout() -> Result<(), Box<dyn Error>> {
+	let output = Command::new(env!("CARGO_BIN_EXE_kdd"))
+		.args(["blocks", "--graph", "mermaid", "-d", APP_1_DIR])
+		.output()?;
+	assert!(output.status.success());
+
+	let stdout = String::from_utf8(output.stdout)?;
+	assert!(stdout.starts_with("graph TD\n"));
+
+	Ok(())
+}